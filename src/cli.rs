@@ -1,16 +1,118 @@
+use std::collections::HashSet;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{CommandFactory, ErrorKind, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
-    #[clap()]
-    pub task: String,
+    #[clap(subcommand)]
+    pub command: Command,
 
-    #[clap(short, long)]
+    #[clap(short, long, global = true)]
     pub log_level: Option<String>,
 
-    #[clap(short, long)]
+    #[clap(short, long, global = true)]
     pub task_file: Option<PathBuf>,
 }
+
+impl Cli {
+    /// Parses the command line, exiting with a usage error if it is invalid
+    pub fn parse_valid() -> Self {
+        let cli = Cli::parse();
+        if let Err(err) = cli.validate() {
+            err.exit();
+        }
+        cli
+    }
+
+    /// Rejects task arguments that are given more than once, which would silently override each other
+    fn validate(&self) -> Result<(), clap::Error> {
+        if let Command::Run {
+            args,
+            trailing_args,
+            ..
+        } = &self.command
+        {
+            let mut names = HashSet::new();
+            for (name, _) in args.iter().chain(trailing_args) {
+                if !names.insert(name) {
+                    return Err(Cli::command().bin_name("inst").error(
+                        ErrorKind::ArgumentConflict,
+                        format!("the task argument '{}' is given more than once", name),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Executes a task by its fully qualified name, e.g. `module.collection.task`
+    Run {
+        #[clap()]
+        task: String,
+
+        /// An argument of the task as `name=value`, can be given multiple times
+        #[clap(short, long = "arg", parse(try_from_str = parse_argument))]
+        args: Vec<(String, String)>,
+
+        /// Further arguments as `name=value`, after a `--`
+        #[clap(last = true, parse(try_from_str = parse_argument))]
+        trailing_args: Vec<(String, String)>,
+
+        /// Prints the interpolated commands instead of executing them
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Statically analyses a task or all executeables of all modules without executing anything
+    Check {
+        #[clap(required_unless_present = "all", conflicts_with = "all")]
+        task: Option<String>,
+
+        /// Checks every task, block and command that can be executed by its name
+        #[clap(long)]
+        all: bool,
+    },
+    /// Lists all modules, collections and tasks with their options and outputs
+    List {
+        /// Prints the list as json
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+fn parse_argument(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.into(), value.into())),
+        _ => Err(format!("expected 'name=value', got '{}'", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(args: &[&str]) -> Result<(), clap::Error> {
+        Cli::try_parse_from(args).unwrap().validate()
+    }
+
+    #[test]
+    fn ok_distinct_arguments() {
+        assert!(validate(&["inst", "run", "test.x", "--arg", "env=a", "--", "region=eu"]).is_ok());
+    }
+
+    #[test]
+    fn nok_repeated_argument() {
+        let err = validate(&["inst", "run", "test.x", "--arg", "env=a", "--arg", "env=b"]);
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn nok_argument_after_separator() {
+        let err = validate(&["inst", "run", "test.x", "--arg", "env=a", "--", "env=b"]);
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+}
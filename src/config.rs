@@ -1,4 +1,7 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use serde::Deserialize;
 
@@ -10,11 +13,65 @@ use figment::{
 #[derive(Deserialize, Debug)]
 pub struct Module {
     pub location: String,
+    /// The directory the commands of the module are executed in, relative ones to the directory `inst`
+    /// is started in
+    pub cwd: Option<String>,
+}
+
+impl Module {
+    /// The configured working directory, defaulting to the directory containing the module file
+    pub fn working_directory(&self) -> PathBuf {
+        match &self.cwd {
+            Some(cwd) => cwd.into(),
+            None => match Path::new(&self.location).parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.into(),
+                _ => PathBuf::from("."),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Runner {
+    #[serde(rename = "type", default = "Runner::default_type")]
+    pub runner_type: String,
+    /// The shell (including its arguments) that shell mode commands are passed to, e.g. `bash -c`
+    pub shell: Option<String>,
+    /// Whether commands are executed through the shell unless specified otherwise
+    #[serde(default)]
+    pub use_shell: bool,
+}
+
+impl Runner {
+    fn default_type() -> String {
+        "command".into()
+    }
+
+    pub fn args(&self) -> HashMap<String, String> {
+        let mut args = HashMap::new();
+        if let Some(shell) = &self.shell {
+            args.insert("shell".into(), shell.clone());
+        }
+        args.insert("use_shell".into(), self.use_shell.to_string());
+        args
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self {
+            runner_type: Self::default_type(),
+            shell: None,
+            use_shell: false,
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Config {
     pub module: HashMap<String, Module>,
+    #[serde(default)]
+    pub runner: HashMap<String, Runner>,
 }
 
 impl Config {
@@ -0,0 +1,163 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use codespan_reporting::{
+    diagnostic::{Diagnostic, Label},
+    files::SimpleFiles,
+    term::{self, termcolor::NoColor, Chars, Config},
+};
+
+use crate::parse::ast::Span;
+
+/// An error that happened at a known location of a task file
+#[derive(Debug)]
+pub struct SpannedError {
+    pub span: Span,
+    pub message: String,
+    source: anyhow::Error,
+}
+
+impl SpannedError {
+    pub fn wrap(
+        span: Span,
+        message: impl Into<String>,
+        source: impl Into<anyhow::Error>,
+    ) -> anyhow::Error {
+        Self {
+            span,
+            message: message.into(),
+            source: source.into(),
+        }
+        .into()
+    }
+}
+
+impl fmt::Display for SpannedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
+
+impl Error for SpannedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+pub trait WithSpan<T> {
+    /// Wraps the error with the location it happened at, like `anyhow::Context::with_context`
+    fn with_span<M, F>(self, span: &Span, message: F) -> anyhow::Result<T>
+    where
+        M: Into<String>,
+        F: FnOnce() -> M;
+}
+
+impl<T, E: Into<anyhow::Error>> WithSpan<T> for Result<T, E> {
+    fn with_span<M, F>(self, span: &Span, message: F) -> anyhow::Result<T>
+    where
+        M: Into<String>,
+        F: FnOnce() -> M,
+    {
+        self.map_err(|err| SpannedError::wrap(span.clone(), message(), err))
+    }
+}
+
+/// Renders the error rustc like: the root cause as the message, the innermost location as an annotated
+/// source snippet and every other cause as a note, ordered from the innermost to the outermost
+pub fn render(error: &anyhow::Error) -> String {
+    let mut files = SimpleFiles::new();
+    let mut file_ids = HashMap::new();
+    let mut labels = Vec::new();
+    let mut notes = Vec::new();
+
+    let causes: Vec<&(dyn Error + 'static)> = error.chain().collect();
+    let (root, contexts) = causes
+        .split_last()
+        .expect("an error has at least one cause");
+
+    for cause in contexts.iter().rev() {
+        match cause.downcast_ref::<SpannedError>() {
+            Some(SpannedError {
+                span:
+                    span @ Span {
+                        source: Some(source),
+                        ..
+                    },
+                message,
+                ..
+            }) if labels.is_empty() => {
+                let file_id = *file_ids
+                    .entry(source.path.clone())
+                    .or_insert_with(|| files.add(source.path.clone(), source.content.clone()));
+                labels.push(
+                    Label::primary(file_id, span.offset..span.offset + span.length)
+                        .with_message(message),
+                );
+            }
+            _ => notes.push(cause.to_string()),
+        }
+    }
+
+    let diagnostic = Diagnostic::error()
+        .with_message(root.to_string())
+        .with_labels(labels)
+        .with_notes(notes);
+
+    let config = Config {
+        chars: Chars::ascii(),
+        ..Config::default()
+    };
+    let mut writer = NoColor::new(Vec::new());
+    match term::emit(&mut writer, &config, &files, &diagnostic) {
+        Ok(()) => String::from_utf8_lossy(&writer.into_inner()).into(),
+        Err(_) => format!("error: {}", root),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use crate::parse::span::{Input, Source};
+
+    use super::*;
+
+    #[test]
+    fn ok_render_span() {
+        let source = Source::new(
+            "test.inst".into(),
+            "module as test;\ntask as x: {\n    run as main: echo ${y};\n};\n".into(),
+        );
+        let input = Input::new_extra(&source.content, Some(&source));
+        let span = Span::at(&input, source.content.len()).narrow(51, 4);
+
+        let error: anyhow::Result<()> = Err(anyhow::anyhow!("undefined variable 'y'"));
+        let error = error
+            .with_span(&span, || "accessed here")
+            .context("analysing task 'x'")
+            .unwrap_err();
+
+        assert_eq!(
+            render(&error),
+            [
+                "error: undefined variable 'y'",
+                "  --> test.inst:3:23",
+                "  |",
+                "3 |     run as main: echo ${y};",
+                "  |                       ^^^^ accessed here",
+                "  |",
+                "  = analysing task 'x'",
+                "",
+                ""
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn ok_render_without_span() {
+        let error: anyhow::Result<()> = Err(anyhow::anyhow!("not found"));
+        let error = error.context("resolving 'x'").unwrap_err();
+
+        assert_eq!(render(&error), "error: not found\n = resolving 'x'\n\n");
+    }
+}
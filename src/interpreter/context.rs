@@ -1,11 +1,11 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, path::PathBuf, rc::Rc, time::Instant};
 
 use crate::{
     runner::{
         interface::RunnerInterface,
         message::{RunnerRequest, RunnerResponse},
     },
-    util::channel::TwoWayChannel,
+    util::{channel::TwoWayChannel, interrupt::Interrupt},
 };
 
 use super::RootNamespace;
@@ -16,6 +16,18 @@ pub type RunnerRequester = TwoWayChannel<RunnerRequest, RunnerResponse>;
 pub struct Context {
     pub root_namespace: RootNamespace,
     pub runner: RunnerInterface,
+    /// Prints the commands instead of executing them
+    pub dry_run: bool,
+    /// Environment variables of the enclosing blocks and tasks, later ones take precedence
+    pub environment: Vec<(String, String)>,
+    /// Names of the environment variables the enclosing blocks and tasks set, while initializing
+    pub environment_names: Vec<String>,
+    /// Working directories of the enclosing executeables, the last one is the current one
+    pub directories: Vec<PathBuf>,
+    /// Deadlines of the enclosing tasks, which all commands within them have to finish by
+    pub deadlines: Vec<Instant>,
+    /// Signals received while executing, which stop the execution before the next command
+    pub interrupt: Interrupt,
 }
 
 impl Context {
@@ -23,6 +35,12 @@ impl Context {
         Self {
             root_namespace,
             runner: RunnerInterface::new(runner_requester),
+            dry_run: false,
+            environment: Vec::new(),
+            environment_names: Vec::new(),
+            directories: Vec::new(),
+            deadlines: Vec::new(),
+            interrupt: Interrupt::global(),
         }
     }
 }
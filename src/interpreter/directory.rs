@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use crate::parse::ast::{VariableBinding, VariableBindings};
+
+use super::context::ContextRef;
+use super::executor::ExecutorError;
+use super::interpolateable::Interpolateable;
+use super::stack::StackRef;
+
+/// The working directory of an executeable, given by the option `cwd: "path"` or by a variable like
+/// `cwd: path`, which is relative to the working directory of the enclosing executeable
+pub struct WorkingDirectory {
+    path: String,
+    interpolateable_path: Option<Interpolateable>,
+}
+
+impl WorkingDirectory {
+    pub fn from_options(options: &Option<VariableBindings>) -> anyhow::Result<Option<Self>> {
+        let binding = options
+            .iter()
+            .flat_map(|options| &options.bindings)
+            .find(|binding| binding.name() == "cwd");
+        let path = match binding {
+            Some(VariableBinding::Literal(_, path)) => path.clone(),
+            Some(VariableBinding::Single(variable) | VariableBinding::Dual(_, variable)) => {
+                format!("${{{}}}", variable)
+            }
+            Some(binding) => {
+                return Err(ExecutorError::InvalidOption("cwd".into(), binding.to_string()).into())
+            }
+            None => return Ok(None),
+        };
+        Ok(Some(Self {
+            interpolateable_path: Interpolateable::new(&path),
+            path,
+        }))
+    }
+
+    pub fn assert_allocated(&self, stack: &StackRef, ctx: &ContextRef) -> anyhow::Result<()> {
+        match &self.interpolateable_path {
+            Some(interpolateable) => interpolateable.assert_variables_allocated(stack, ctx, None),
+            None => Ok(()),
+        }
+    }
+
+    pub fn resolve(&self, stack: &StackRef, ctx: &ContextRef) -> anyhow::Result<PathBuf> {
+        let path = match &self.interpolateable_path {
+            Some(interpolateable) => {
+                let mut target = String::new();
+                interpolateable.interpolate(stack, ctx, &mut target)?;
+                target
+            }
+            None => self.path.clone(),
+        };
+        Ok(current(ctx).join(path))
+    }
+}
+
+/// The working directory of the innermost executeable that is being executed
+pub fn current(ctx: &ContextRef) -> PathBuf {
+    ctx.borrow()
+        .directories
+        .last()
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Resolves the working directory of an executeable, which is `None` if it does not change it
+pub fn resolve(
+    directory: &Option<WorkingDirectory>,
+    stack: &StackRef,
+    ctx: &ContextRef,
+) -> anyhow::Result<Option<PathBuf>> {
+    match directory {
+        Some(directory) => Ok(Some(directory.resolve(stack, ctx)?)),
+        None => Ok(None),
+    }
+}
+
+/// Runs `f` with the directory as working directory of everything executed within it
+pub fn scoped<T>(
+    directory: Option<PathBuf>,
+    ctx: &ContextRef,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let directory = match directory {
+        Some(directory) => directory,
+        None => return f(),
+    };
+
+    ctx.borrow_mut().directories.push(directory);
+    let result = f();
+    ctx.borrow_mut().directories.pop();
+    result
+}
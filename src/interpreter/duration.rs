@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+/// Parses a duration like `500ms`, `5s`, `2m` or `1h`, where a plain number is in seconds
+pub fn parse(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let amount: u64 = value[..split].parse().ok()?;
+    match &value[split..] {
+        "ms" => Some(Duration::from_millis(amount)),
+        "" | "s" => Some(Duration::from_secs(amount)),
+        "m" => amount.checked_mul(60).map(Duration::from_secs),
+        "h" => amount.checked_mul(60 * 60).map(Duration::from_secs),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ok_units() {
+        assert_eq!(parse("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse("5s"), Some(Duration::from_secs(5)));
+        assert_eq!(parse("5"), Some(Duration::from_secs(5)));
+        assert_eq!(parse("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse("1h"), Some(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn nok_invalid() {
+        assert_eq!(parse("s"), None);
+        assert_eq!(parse("5d"), None);
+        assert_eq!(parse("-5s"), None);
+        assert_eq!(parse("99999999999999999h"), None);
+    }
+}
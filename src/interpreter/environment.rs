@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::parse::ast::{VariableBinding, VariableBindings};
+
+use super::context::ContextRef;
+use super::stack::StackRef;
+
+#[derive(Error, Debug)]
+pub enum EnvironmentError {
+    #[error("the environment variable '{0}' can not be set to a list")]
+    NestedBinding(String),
+}
+
+/// The environment variables set for all processes spawned by an executeable, given by the option
+/// `env: (KEY: variable, OTHER: "literal")`
+pub struct Environment {
+    bindings: VariableBindings,
+}
+
+impl Environment {
+    pub fn from_options(options: &Option<VariableBindings>) -> anyhow::Result<Option<Self>> {
+        let bindings = match options
+            .as_ref()
+            .and_then(|options| options.find_nested("env"))
+        {
+            Some(bindings) => bindings.clone(),
+            None => return Ok(None),
+        };
+
+        for binding in &bindings.bindings {
+            if let VariableBinding::Nested(name, _) = binding {
+                return Err(EnvironmentError::NestedBinding(name.clone()).into());
+            }
+        }
+
+        Ok(Some(Self { bindings }))
+    }
+
+    /// Checks whether all variables the environment variables are set from are allocated
+    pub fn assert_allocated(&self, stack: &StackRef) -> anyhow::Result<()> {
+        for binding in &self.bindings.bindings {
+            if let VariableBinding::Single(variable) | VariableBinding::Dual(_, variable) = binding
+            {
+                stack.borrow().assert_allocated(variable)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The names of the environment variables it sets
+    pub fn names(&self) -> Vec<String> {
+        self.bindings
+            .bindings
+            .iter()
+            .map(|binding| binding.name().into())
+            .collect()
+    }
+
+    pub fn resolve(&self, stack: &StackRef) -> anyhow::Result<Vec<(String, String)>> {
+        let mut variables = Vec::new();
+        for binding in &self.bindings.bindings {
+            let (name, value) = match binding {
+                VariableBinding::Single(variable) => (variable, stack.borrow().get(variable)?),
+                VariableBinding::Dual(name, variable) => (name, stack.borrow().get(variable)?),
+                VariableBinding::Literal(name, literal) => (name, literal.clone()),
+                VariableBinding::Default(variable, default) => (
+                    variable,
+                    stack
+                        .borrow()
+                        .get(variable)
+                        .unwrap_or_else(|_| default.clone()),
+                ),
+                VariableBinding::Nested(name, _) => {
+                    return Err(EnvironmentError::NestedBinding(name.clone()).into())
+                }
+            };
+            variables.push((name.clone(), value));
+        }
+        Ok(variables)
+    }
+}
+
+/// The variables of the environment, if the executeable has one
+pub fn resolve(
+    environment: &Option<Environment>,
+    stack: &StackRef,
+) -> anyhow::Result<Vec<(String, String)>> {
+    match environment {
+        Some(environment) => environment.resolve(stack),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Runs `f` with the variables applied to every command executed within it, which stays in effect
+/// for called tasks as well
+pub fn scoped<T>(
+    variables: Vec<(String, String)>,
+    ctx: &ContextRef,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let previous_len = ctx.borrow().environment.len();
+    ctx.borrow_mut().environment.extend(variables);
+    let result = f();
+    ctx.borrow_mut().environment.truncate(previous_len);
+    result
+}
+
+/// Runs `f`, the initialization of the enclosed executeables, with the names of the variables
+/// declared, so interpolating them is checked like the environment `inst` was started with
+pub fn declared<T>(
+    environment: &Option<Environment>,
+    ctx: &ContextRef,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let previous_len = ctx.borrow().environment_names.len();
+    if let Some(environment) = environment {
+        ctx.borrow_mut()
+            .environment_names
+            .extend(environment.names());
+    }
+    let result = f();
+    ctx.borrow_mut().environment_names.truncate(previous_len);
+    result
+}
+
+/// The environment of a command, where its own variables take precedence over the ones of the
+/// enclosing executeables
+pub fn command_environment(
+    environment: &Option<Environment>,
+    stack: &StackRef,
+    ctx: &ContextRef,
+) -> anyhow::Result<HashMap<String, String>> {
+    let mut variables: HashMap<String, String> = ctx.borrow().environment.iter().cloned().collect();
+    variables.extend(resolve(environment, stack)?);
+    Ok(variables)
+}
@@ -0,0 +1,75 @@
+use crate::diagnostic::WithSpan;
+use crate::interpreter::context::ContextRef;
+use crate::interpreter::interpolateable::Interpolateable;
+use crate::interpreter::stack::StackRef;
+use crate::parse::ast::{Executeable, ExecuteableType, Span};
+
+use super::{Executor, ExecutorError};
+
+pub struct AssignmentExecutor {
+    variable: String,
+    value: String,
+    interpolateable_value: Option<Interpolateable>,
+    span: Span,
+}
+
+impl AssignmentExecutor {
+    pub fn new(input: Executeable, _path: String) -> anyhow::Result<Self> {
+        if let ExecuteableType::Assignment { variable, value } = input.executeable_type {
+            Ok(AssignmentExecutor {
+                interpolateable_value: Interpolateable::new(&value),
+                variable,
+                value,
+                span: input.span,
+            })
+        } else {
+            Err(ExecutorError::WrongExecutorType(input.executeable_type).into())
+        }
+    }
+
+    /// The location of the value within the quotes, unless it contained escapes
+    fn value_span(&self) -> Option<Span> {
+        let value_span = self
+            .span
+            .tail(self.value.len() + 1)
+            .narrow(0, self.value.len());
+        let verbatim = value_span.source.is_none() || value_span.text() == Some(&self.value);
+        verbatim.then_some(value_span)
+    }
+
+    pub fn error_context(&self) -> String {
+        format!("assigning '{}'", self.variable)
+    }
+}
+
+impl Executor for AssignmentExecutor {
+    fn init(&mut self, stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if let Some(interpolateable) = &self.interpolateable_value {
+            interpolateable
+                .assert_variables_allocated(&stack, &ctx, self.value_span().as_ref())
+                .with_span(&self.span, || self.error_context())?;
+        }
+
+        stack.borrow_mut().allocate(self.variable.clone());
+
+        Ok(())
+    }
+
+    fn execute(&self, stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        let value = match &self.interpolateable_value {
+            None => self.value.clone(),
+            Some(interpolateable) => {
+                let mut target = String::new();
+                interpolateable
+                    .interpolate(&stack, &ctx, &mut target)
+                    .with_span(&self.span, || self.error_context())?;
+                target
+            }
+        };
+
+        stack
+            .borrow_mut()
+            .set(self.variable.clone(), value)
+            .with_span(&self.span, || self.error_context())
+    }
+}
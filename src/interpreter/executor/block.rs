@@ -1,36 +1,53 @@
-use anyhow::Context;
 use log::debug;
 
+use crate::diagnostic::WithSpan;
 use crate::interpreter::context::ContextRef;
+use crate::interpreter::directory::{self, WorkingDirectory};
+use crate::interpreter::environment::{self, Environment};
 use crate::interpreter::stack::StackRef;
 use crate::interpreter::variables::Variables;
-use crate::parse::ast::{Executeable, ExecuteableType};
+use crate::parse::ast::{Executeable, ExecuteableType, Span};
 
-use super::{get_executor, DynExecutor, Executor, ExecutorError, Stack};
+use super::{child_path, get_executor, DynExecutor, Executor, ExecutorError, Stack};
 
 pub struct BlockExecutor {
     name: String,
+    path: String,
     variables: Variables,
+    environment: Option<Environment>,
+    directory: Option<WorkingDirectory>,
     executeables: Vec<Executeable>,
     executors: Vec<DynExecutor>,
     stack: Option<StackRef>,
+    span: Span,
 }
 
 impl BlockExecutor {
-    pub fn new(input: Executeable) -> anyhow::Result<BlockExecutor> {
+    pub fn new(input: Executeable, path: String) -> anyhow::Result<BlockExecutor> {
         if let ExecuteableType::Block { executeables } = input.executeable_type {
+            let environment = Environment::from_options(&input.options)
+                .with_span(&input.span, || "parsing options")?;
             Ok(BlockExecutor {
                 name: input.name,
+                path,
                 variables: Variables::new(input.output_variables),
+                environment,
+                directory: WorkingDirectory::from_options(&input.options)
+                    .with_span(&input.span, || "parsing options")?,
                 executeables,
                 executors: Vec::new(),
                 stack: None,
+                span: input.span,
             })
         } else {
             Err(ExecutorError::WrongExecutorType(input.executeable_type).into())
         }
     }
 
+    pub fn init_context(&self, index: usize) -> String {
+        format!("analysing block '{}' at index '{}'", self.name, index)
+    }
+
     pub fn error_context(&self, index: usize) -> String {
         format!("executing block '{}' at index '{}'", self.name, index)
     }
@@ -38,32 +55,60 @@ impl BlockExecutor {
 
 impl Executor for BlockExecutor {
     fn init(&mut self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if let Some(environment) = &self.environment {
+            environment
+                .assert_allocated(&parent_stack)
+                .with_span(&self.span, || "checking the env option")?;
+        }
+        if let Some(directory) = &self.directory {
+            directory
+                .assert_allocated(&parent_stack, &ctx)
+                .with_span(&self.span, || "checking the cwd option")?;
+        }
+
         let mut child_stack: StackRef = Stack::inherit_new(&parent_stack).into();
 
-        for executeable in self.executeables.drain(..) {
-            let mut executor = get_executor(executeable, child_stack.clone())?;
-            executor.init(child_stack.clone(), ctx.clone())?;
-            self.executors.push(executor);
-        }
+        let executeables: Vec<Executeable> = self.executeables.drain(..).collect();
+        self.executors = environment::declared(&self.environment, &ctx, || {
+            let mut executors = Vec::new();
+            for (counter, executeable) in executeables.into_iter().enumerate() {
+                let path = child_path(&self.path, &executeable.name);
+                let mut executor = get_executor(executeable, path, child_stack.clone())?;
+                executor
+                    .init(child_stack.clone(), ctx.clone())
+                    .with_span(&self.span, || self.init_context(counter))?;
+                executors.push(executor);
+            }
+            Ok(executors)
+        })?;
 
         self.variables
-            .allocate_and_check_all(&mut parent_stack, &mut child_stack)?;
+            .allocate_and_check_all(&mut parent_stack, &mut child_stack)
+            .with_span(&self.span, || "checking the output variables")?;
 
         self.stack = Some(child_stack);
 
         Ok(())
     }
 
-    #[allow(clippy::needless_collect)]
-    fn execute(&mut self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
-        if let Some(mut child_stack) = self.stack.take() {
+    fn execute(&self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if let Some(stack) = &self.stack {
+            let mut child_stack: StackRef = stack.borrow().instantiate(&parent_stack).into();
             debug!("{}: {{", &self.name);
-            let executors: Vec<DynExecutor> = self.executors.drain(..).collect();
-            for (counter, mut executor) in executors.into_iter().enumerate() {
-                executor
-                    .execute(child_stack.clone(), ctx.clone())
-                    .with_context(|| self.error_context(counter))?;
-            }
+            let environment = environment::resolve(&self.environment, &parent_stack)
+                .with_span(&self.span, || "setting the env option")?;
+            let directory = directory::resolve(&self.directory, &parent_stack, &ctx)
+                .with_span(&self.span, || "setting the cwd option")?;
+            environment::scoped(environment, &ctx, || {
+                directory::scoped(directory, &ctx, || {
+                    for (counter, executor) in self.executors.iter().enumerate() {
+                        executor
+                            .execute(child_stack.clone(), ctx.clone())
+                            .with_span(&self.span, || self.error_context(counter))?;
+                    }
+                    Ok(())
+                })
+            })?;
             self.variables
                 .carry_over(&mut parent_stack, &mut child_stack)?;
             debug!("}}\n");
@@ -1,30 +1,50 @@
-use anyhow::Context;
-
+use crate::diagnostic::WithSpan;
 use crate::interpreter::context::ContextRef;
+use crate::interpreter::directory;
+use crate::interpreter::retry::{self, RetryPolicy};
 use crate::interpreter::stack::StackRef;
 use crate::interpreter::variables::Variables;
-use crate::parse::ast::{Executeable, ExecuteableType};
+use crate::parse::ast::{Executeable, ExecuteableType, Span};
 
-use super::{get_executor, DynExecutor, Executor, ExecutorError, Stack};
+use super::{
+    declared_arguments, get_executor, quote_all, unsupported_option, without_options, DynExecutor,
+    Executor, ExecutorError, Stack,
+};
 
 struct Executors {
     calle: DynExecutor,
+    /// The stack the arguments are passed in
     stack: StackRef,
 }
 
 pub struct CallExecutor {
     variables: Variables,
+    arguments: Variables,
     target_name: String,
+    retry: Option<RetryPolicy>,
     executors: Option<Executors>,
+    span: Span,
 }
 
 impl CallExecutor {
-    pub fn new(input: Executeable) -> anyhow::Result<Self> {
+    pub fn new(input: Executeable, _path: String) -> anyhow::Result<Self> {
         if let ExecuteableType::Call { target } = input.executeable_type {
+            if let Some(option) = unsupported_option(&input.options, &retry::OPTIONS) {
+                return Err(ExecutorError::UnsupportedOption(
+                    option.into(),
+                    "call".into(),
+                ))
+                .with_span(&input.span, || "parsing options");
+            }
+            let retry = RetryPolicy::from_options(&input.options)
+                .with_span(&input.span, || "parsing options")?;
             let exe = CallExecutor {
                 variables: Variables::new(input.output_variables),
+                arguments: Variables::new(without_options(input.options)),
                 target_name: target,
+                retry,
                 executors: None,
+                span: input.span,
             };
             Ok(exe)
         } else {
@@ -32,6 +52,22 @@ impl CallExecutor {
         }
     }
 
+    /// Checks that every passed argument is declared by the callee
+    fn check_arguments(&self, calle_executeable: &Executeable) -> anyhow::Result<()> {
+        let declared = declared_arguments(calle_executeable);
+        for name in self.arguments.names() {
+            if !declared.iter().any(|declared| &declared.name == name) {
+                return Err(ExecutorError::UnknownArgument(
+                    name.into(),
+                    self.target_name.clone(),
+                    quote_all(declared.iter().map(|declared| &declared.name)),
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
     pub fn error_context(&self) -> String {
         format!("calling: '{}'", self.target_name)
     }
@@ -43,17 +79,26 @@ impl Executor for CallExecutor {
             .borrow()
             .root_namespace
             .resolve_name(&self.target_name)
-            .with_context(|| self.error_context())?
+            .with_span(&self.span, || self.error_context())?
             .clone();
+        self.check_arguments(&calle_executeable)
+            .with_span(&self.span, || "passing the arguments")?;
         let mut calle_executor =
-            get_executor(calle_executeable, stack.clone()).with_context(|| self.error_context())?;
+            get_executor(calle_executeable, self.target_name.clone(), stack.clone())
+                .with_span(&self.span, || self.error_context())?;
 
         let mut child_stack: StackRef = Stack::inherit_new(&stack).into();
+        self.arguments
+            .allocate_and_check_all(&mut child_stack, &mut stack)
+            .with_span(&self.span, || "passing the arguments")?;
 
-        calle_executor.init(child_stack.clone(), ctx.clone())?;
+        calle_executor
+            .init(child_stack.clone(), ctx.clone())
+            .with_span(&self.span, || self.error_context())?;
 
         self.variables
-            .allocate_and_check_all(&mut stack, &mut child_stack)?;
+            .allocate_and_check_all(&mut stack, &mut child_stack)
+            .with_span(&self.span, || "checking the output variables")?;
         self.executors = Some(Executors {
             calle: calle_executor,
             stack: child_stack,
@@ -62,15 +107,29 @@ impl Executor for CallExecutor {
         Ok(())
     }
 
-    fn execute(&mut self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
-        if let Some(mut executors) = self.executors.take() {
-            executors
-                .calle
-                .execute(executors.stack.clone(), ctx.clone())
-                .with_context(|| self.error_context())?;
+    fn execute(&self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if let Some(executors) = &self.executors {
+            let mut child_stack: StackRef =
+                executors.stack.borrow().instantiate(&parent_stack).into();
+            self.arguments
+                .carry_over(&mut child_stack, &mut parent_stack)
+                .with_span(&self.span, || "passing the arguments")?;
+
+            // The called executeable runs in the working directory of its own module
+            let calle_directory = ctx
+                .borrow()
+                .root_namespace
+                .working_directory(&self.target_name)
+                .into();
+            directory::scoped(Some(calle_directory), &ctx, || {
+                retry::retried(&self.retry, &self.target_name, &ctx, || {
+                    executors.calle.execute(child_stack.clone(), ctx.clone())
+                })
+            })
+            .with_span(&self.span, || self.error_context())?;
 
             self.variables
-                .carry_over(&mut parent_stack, &mut executors.stack)?;
+                .carry_over(&mut parent_stack, &mut child_stack)?;
 
             Ok(())
         } else {
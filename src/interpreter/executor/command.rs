@@ -1,43 +1,118 @@
-use anyhow::Context;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 use log::{debug, error, info, warn};
 
+use crate::diagnostic::WithSpan;
 use crate::interpreter::context::ContextRef;
+use crate::interpreter::directory::{self, WorkingDirectory};
+use crate::interpreter::environment::{command_environment, Environment};
 use crate::interpreter::interpolateable::Interpolateable;
+use crate::interpreter::retry::{self, RetryPolicy};
 use crate::interpreter::stack::StackRef;
+use crate::interpreter::timeout;
 use crate::interpreter::variables::Variables;
-use crate::parse::ast::{Executeable, ExecuteableType};
+use crate::logger;
+use crate::parse::ast::{Executeable, ExecuteableType, Span, VariableBindings};
+use crate::runner::interface::RunnerInterfaceError;
+use crate::runner::message::{
+    action::{Encoding, RunAction},
+    result::{Exit, RunResult, Stream},
+};
 
 use super::{Executor, ExecutorError, Stack};
 
 pub struct CommandExecutor {
+    path: String,
     variables: Variables,
+    options: Option<VariableBindings>,
     cmd: String,
     interpolateable_cmd: Option<Interpolateable>,
     stdin_variable: Option<String>,
+    environment: Option<Environment>,
+    directory: Option<WorkingDirectory>,
+    runner_name: String,
+    use_shell: Option<bool>,
+    allowed_status: Vec<i32>,
+    ignore_failure: bool,
     trim_stdout: bool,
     trim_stderr: bool,
+    encoding: Encoding,
+    interactive: bool,
+    retry: Option<RetryPolicy>,
+    timeout: Option<Duration>,
     stack: Option<StackRef>,
+    span: Span,
 }
 
 impl CommandExecutor {
-    pub fn new(input: Executeable) -> anyhow::Result<Self> {
+    pub fn new(input: Executeable, path: String) -> anyhow::Result<Self> {
         if let ExecuteableType::Command { cmd } = input.executeable_type {
-            let (stdin_variable, trim_stdout, trim_stderr) = match input.options {
+            let (
+                stdin_variable,
+                runner_name,
+                use_shell,
+                allowed_status,
+                ignore_failure,
+                trim_stdout,
+                trim_stderr,
+                encoding,
+                interactive,
+            ) = match &input.options {
                 Some(bindings) => (
                     bindings.find("stdin").map(|val| val.into()),
+                    bindings.find("runner").unwrap_or("default").into(),
+                    Self::find_use_shell(bindings),
+                    Self::find_allowed_status(bindings)
+                        .with_span(&input.span, || "parsing options")?,
+                    bindings.find("ignore_failure").is_some(),
                     bindings.find("trim_stdout").is_some(),
                     bindings.find("trim_stderr").is_some(),
+                    Self::find_encoding(bindings).with_span(&input.span, || "parsing options")?,
+                    bindings.find("interactive").is_some(),
+                ),
+                None => (
+                    None,
+                    "default".into(),
+                    None,
+                    vec![0],
+                    false,
+                    false,
+                    false,
+                    Encoding::default(),
+                    false,
                 ),
-                None => (None, false, false),
             };
+            let environment = Environment::from_options(&input.options)
+                .with_span(&input.span, || "parsing options")?;
+            let directory = WorkingDirectory::from_options(&input.options)
+                .with_span(&input.span, || "parsing options")?;
+            let retry = RetryPolicy::from_options(&input.options)
+                .with_span(&input.span, || "parsing options")?;
+            let timeout = timeout::from_options(&input.options)
+                .with_span(&input.span, || "parsing options")?;
             let mut exe = CommandExecutor {
+                path,
                 variables: Variables::new(input.output_variables),
+                options: input.options,
                 cmd,
                 interpolateable_cmd: None,
                 stdin_variable,
+                environment,
+                directory,
+                runner_name,
+                use_shell,
+                allowed_status,
+                ignore_failure,
                 trim_stdout,
                 trim_stderr,
+                encoding,
+                interactive,
+                retry,
+                timeout,
                 stack: None,
+                span: input.span,
             };
             exe.interpolateable_cmd = Interpolateable::new(&exe.cmd);
             Ok(exe)
@@ -46,34 +121,252 @@ impl CommandExecutor {
         }
     }
 
-    pub fn interpolate(&self, stack: &StackRef) -> anyhow::Result<String> {
+    fn find_use_shell(bindings: &VariableBindings) -> Option<bool> {
+        if bindings.find("shell").is_some() {
+            Some(true)
+        } else if bindings.find("no_shell").is_some() {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    fn find_allowed_status(bindings: &VariableBindings) -> anyhow::Result<Vec<i32>> {
+        match bindings.find("allow_status") {
+            Some(allowed_status) => allowed_status
+                .split(',')
+                .map(|status| {
+                    status.trim().parse().map_err(|_| {
+                        ExecutorError::InvalidOption("allow_status".into(), allowed_status.into())
+                            .into()
+                    })
+                })
+                .collect(),
+            None => Ok(vec![0]),
+        }
+    }
+
+    fn find_encoding(bindings: &VariableBindings) -> anyhow::Result<Encoding> {
+        match bindings.find("encoding") {
+            Some("lossy") | None => Ok(Encoding::Lossy),
+            Some("raw") => Ok(Encoding::Raw),
+            Some(other) => {
+                Err(ExecutorError::InvalidOption("encoding".into(), other.into()).into())
+            }
+        }
+    }
+
+    fn is_status_allowed(&self, exit: &Exit) -> bool {
+        exit.status()
+            .parse()
+            .map(|status| self.allowed_status.contains(&status))
+            .unwrap_or(false)
+    }
+
+    /// The output of an interactive command goes straight to the terminal, so it can not be bound,
+    /// and it stays in the process group of `inst`, so it can not be terminated as a whole
+    fn assert_interactive(&self) -> anyhow::Result<()> {
+        if self.stdin_variable.is_some() {
+            return Err(ExecutorError::InteractiveStdin.into());
+        }
+        if self.timeout.is_some() {
+            return Err(ExecutorError::InteractiveTimeout.into());
+        }
+        match ["stdout", "stderr"]
+            .into_iter()
+            .find(|output| self.variables.binds(output))
+        {
+            Some(output) => Err(ExecutorError::InteractiveOutput(output.into()).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// A failed command is tolerated if explicitly allowed or if its status is inspected by binding it
+    fn is_failure_tolerated(&self, exit: &Exit) -> bool {
+        self.ignore_failure || self.variables.binds("status") || self.is_status_allowed(exit)
+    }
+
+    pub fn interpolate(&self, stack: &StackRef, ctx: &ContextRef) -> anyhow::Result<String> {
         match &self.interpolateable_cmd {
             None => Ok(self.cmd.clone()),
             Some(inter) => {
                 let mut target = String::new();
                 inter
-                    .interpolate(stack, &mut target)
-                    .with_context(|| self.error_context())?;
+                    .interpolate(stack, ctx, &mut target)
+                    .with_span(&self.span, || self.error_context())?;
                 Ok(target)
             }
         }
     }
 
+    /// The location of the command itself, unless it is not written verbatim like a dedented multi-line command
+    fn cmd_span(&self) -> Option<Span> {
+        let cmd_span = self.span.tail(self.cmd.len());
+        let verbatim = cmd_span.source.is_none() || cmd_span.text() == Some(&self.cmd);
+        verbatim.then_some(cmd_span)
+    }
+
+    fn run(
+        &self,
+        interpolated: String,
+        stdin: Option<String>,
+        env: HashMap<String, String>,
+        cwd: PathBuf,
+        ctx: &ContextRef,
+    ) -> anyhow::Result<RunResult> {
+        debug!("$  {}", &interpolated);
+
+        let action = RunAction {
+            command: interpolated.clone(),
+            use_shell: self.use_shell,
+            stdin,
+            env,
+            cwd: Some(cwd),
+            timeout: None,
+            encoding: self.encoding,
+            interactive: self.interactive,
+            trim_stdout: self.trim_stdout,
+            trim_stderr: self.trim_stderr,
+        };
+        // A retried attempt is a failure even if the status is bound, so only the outputs of the
+        // final attempt are bound
+        let result = retry::retried_on(
+            &self.retry,
+            &self.path,
+            ctx,
+            || self.attempt(action.clone(), ctx),
+            |result| match result {
+                Ok(result) if !self.is_status_allowed(&result.exit) => Some(result.exit),
+                _ => None,
+            },
+        )?;
+
+        let failed = !self.is_failure_tolerated(&result.exit);
+        if failed {
+            error!("$? {}", result.exit);
+        } else if result.exit != Exit::Code(0) {
+            warn!("$? {}", result.exit);
+        }
+
+        if failed {
+            return Err(ExecutorError::CommandFailed(interpolated, result.exit))
+                .with_span(&self.span, || self.error_context());
+        }
+
+        Ok(result)
+    }
+
+    /// Runs the command once, within the time that is left of the timeouts
+    fn attempt(&self, mut action: RunAction, ctx: &ContextRef) -> anyhow::Result<RunResult> {
+        // A signal received before, e.g. while waiting to retry, ends the execution right away
+        self.assert_not_interrupted(ctx)?;
+        action.timeout = timeout::remaining(self.timeout, ctx);
+        let timeout = action.timeout;
+        if self.interactive {
+            logger::hold_back();
+        }
+        let response = ctx
+            .borrow()
+            .runner
+            .run(self.runner_name.clone(), action, |stream, line| {
+                self.print_line(stream, line)
+            });
+        if self.interactive {
+            logger::release();
+        }
+        let result = match response {
+            Ok(result) => result,
+            Err(RunnerInterfaceError::TimedOut(result)) => {
+                error!("$  timed out after {:?}", timeout.unwrap_or_default());
+                result
+            }
+            Err(err) => {
+                return Err(ExecutorError::RunnerInterfaceError(err))
+                    .with_span(&self.span, || self.error_context())
+            }
+        };
+        // The command most likely failed because of the forwarded signal, which is not retried
+        self.assert_not_interrupted(ctx)?;
+        Ok(result)
+    }
+
+    /// Takes a signal received by `inst`, so the finally stages after this command are still executed
+    fn assert_not_interrupted(&self, ctx: &ContextRef) -> anyhow::Result<()> {
+        match ctx.borrow().interrupt.take() {
+            Some(signal) => Err(ExecutorError::Interrupted(signal))
+                .with_span(&self.span, || self.error_context()),
+            None => Ok(()),
+        }
+    }
+
+    /// Prints a line of the output while the command is running, prefixed by the executeable
+    fn print_line(&self, stream: Stream, line: &str) {
+        match stream {
+            Stream::Stdout => info!("{} 1> {}", self.path, line),
+            Stream::Stderr => warn!("{} 2> {}", self.path, line),
+        }
+    }
+
+    /// Prints the command to stdout, as it is the output of a dry run instead of a log message
+    fn print_dry_run(&self, interpolated: &str, cwd: &Path) -> anyhow::Result<()> {
+        let options = match &self.options {
+            Some(options) => format!(" with ({})", options),
+            None => String::new(),
+        };
+        crate::print_stdout(&format!(
+            "{} on runner '{}' in '{}'{}:\n    {}\n",
+            self.path,
+            self.runner_name,
+            cwd.display(),
+            options,
+            interpolated.replace('\n', "\n    ")
+        ))
+    }
+
+    /// The outputs of a command that was not executed, where a dry run assumes it succeeded
+    fn placeholder_result(&self) -> RunResult {
+        RunResult {
+            stdout: format!("<stdout of {}>", self.path),
+            stderr: format!("<stderr of {}>", self.path),
+            exit: Exit::Code(0),
+        }
+    }
+
     pub fn error_context(&self) -> String {
         format!("executing command: '{}'", self.cmd)
     }
 }
 
 impl Executor for CommandExecutor {
-    fn init(&mut self, mut stack: StackRef, _ctx: ContextRef) -> anyhow::Result<()> {
+    fn init(&mut self, mut stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if self.interactive {
+            self.assert_interactive()
+                .with_span(&self.span, || "checking the interactive option")?;
+        }
+
         if let Some(interpolateable) = &self.interpolateable_cmd {
             interpolateable
-                .assert_variables_allocated(&stack)
-                .with_context(|| self.error_context())?;
+                .assert_variables_allocated(&stack, &ctx, self.cmd_span().as_ref())
+                .with_span(&self.span, || self.error_context())?;
         }
 
         if let Some(stdin_variable) = &self.stdin_variable {
-            stack.borrow().assert_allocated(stdin_variable)?;
+            stack
+                .borrow()
+                .assert_allocated(stdin_variable)
+                .with_span(&self.span, || "checking the stdin option")?;
+        }
+
+        if let Some(environment) = &self.environment {
+            environment
+                .assert_allocated(&stack)
+                .with_span(&self.span, || "checking the env option")?;
+        }
+
+        if let Some(directory) = &self.directory {
+            directory
+                .assert_allocated(&stack, &ctx)
+                .with_span(&self.span, || "checking the cwd option")?;
         }
 
         let mut child_stack: StackRef = Stack::inherit_new(&stack).into();
@@ -85,51 +378,54 @@ impl Executor for CommandExecutor {
         }
 
         self.variables
-            .allocate_and_check_all(&mut stack, &mut child_stack)?;
+            .allocate_and_check_all(&mut stack, &mut child_stack)
+            .with_span(&self.span, || "checking the output variables")?;
         self.stack = Some(child_stack);
 
         Ok(())
     }
 
-    fn execute(&mut self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
-        if let Some(mut child_stack) = self.stack.clone() {
-            let interpolated = self.interpolate(&parent_stack)?;
+    fn execute(&self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if let Some(stack) = &self.stack {
+            let mut child_stack: StackRef = stack.borrow().instantiate(&parent_stack).into();
+            let interpolated = self.interpolate(&parent_stack, &ctx)?;
 
-            debug!("$  {}", &interpolated);
+            let stdin = match &self.stdin_variable {
+                Some(stdin_variable) => Some(
+                    parent_stack
+                        .borrow()
+                        .get(stdin_variable)
+                        .with_span(&self.span, || self.error_context())?,
+                ),
+                None => None,
+            };
 
-            let result = ctx
-                .borrow()
-                .runner
-                .run(
-                    "default".into(),
-                    interpolated,
-                    self.trim_stdout,
-                    self.trim_stderr,
-                )
-                .map_err(|err| ExecutorError::RunnerInterfaceError(err))?;
-
-            if result.status != "0" {
-                error!("$? {}", result.status);
-            }
+            let cwd = directory::resolve(&self.directory, &parent_stack, &ctx)
+                .with_span(&self.span, || self.error_context())?
+                .unwrap_or_else(|| directory::current(&ctx));
 
-            if !result.stdout.is_empty() {
-                info!("1> {}", &result.stdout);
-            }
-            if !result.stderr.is_empty() {
-                warn!("2> {}", &result.stderr);
-            }
+            let dry_run = ctx.borrow().dry_run;
+            let result = if dry_run {
+                self.print_dry_run(&interpolated, &cwd)
+                    .with_span(&self.span, || self.error_context())?;
+                self.placeholder_result()
+            } else {
+                let env = command_environment(&self.environment, &parent_stack, &ctx)
+                    .with_span(&self.span, || self.error_context())?;
+                self.run(interpolated, stdin, env, cwd, &ctx)?
+            };
 
             {
                 let mut child_stack_ref = child_stack.borrow_mut();
                 child_stack_ref
                     .set("stdout".into(), result.stdout)
-                    .with_context(|| self.error_context())?;
+                    .with_span(&self.span, || self.error_context())?;
                 child_stack_ref
                     .set("stderr".into(), result.stderr)
-                    .with_context(|| self.error_context())?;
+                    .with_span(&self.span, || self.error_context())?;
                 child_stack_ref
-                    .set("status".into(), result.status)
-                    .with_context(|| self.error_context())?;
+                    .set("status".into(), result.exit.status())
+                    .with_span(&self.span, || self.error_context())?;
             }
 
             self.variables
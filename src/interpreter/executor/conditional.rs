@@ -0,0 +1,218 @@
+use std::collections::HashSet;
+
+use log::debug;
+
+use crate::diagnostic::WithSpan;
+use crate::interpreter::context::ContextRef;
+use crate::interpreter::directory;
+use crate::interpreter::interpolateable::Interpolateable;
+use crate::interpreter::stack::StackRef;
+use crate::parse::ast::{Condition, Executeable, ExecuteableType, Span};
+
+use super::{child_path, get_executor, DynExecutor, Executor, ExecutorError, Stack};
+
+struct Branch {
+    executors: Vec<DynExecutor>,
+    stack: StackRef,
+    /// The variables of the branch that are carried over to the parent stack after it executed
+    carried: Vec<String>,
+}
+
+pub struct ConditionalExecutor {
+    path: String,
+    condition: Condition,
+    executeables: Option<(Vec<Executeable>, Vec<Executeable>)>,
+    branches: Option<(Branch, Branch)>,
+    span: Span,
+}
+
+impl ConditionalExecutor {
+    pub fn new(input: Executeable, path: String) -> anyhow::Result<Self> {
+        if let ExecuteableType::Conditional {
+            condition,
+            then,
+            otherwise,
+        } = input.executeable_type
+        {
+            Ok(ConditionalExecutor {
+                path,
+                condition,
+                executeables: Some((then, otherwise.unwrap_or_default())),
+                branches: None,
+                span: input.span,
+            })
+        } else {
+            Err(ExecutorError::WrongExecutorType(input.executeable_type).into())
+        }
+    }
+
+    fn init_branch(
+        &self,
+        executeables: Vec<Executeable>,
+        parent_stack: &StackRef,
+        ctx: &ContextRef,
+        branch: &'static str,
+    ) -> anyhow::Result<Branch> {
+        let stack: StackRef = Stack::inherit_new(parent_stack).into();
+        let mut executors = Vec::new();
+        for (counter, executeable) in executeables.into_iter().enumerate() {
+            let path = child_path(&self.path, &executeable.name);
+            let mut executor = get_executor(executeable, path, stack.clone())?;
+            executor
+                .init(stack.clone(), ctx.clone())
+                .with_span(&self.span, || self.init_context(branch, counter))?;
+            executors.push(executor);
+        }
+        Ok(Branch {
+            executors,
+            stack,
+            carried: Vec::new(),
+        })
+    }
+
+    pub fn init_context(&self, branch: &str, index: usize) -> String {
+        format!(
+            "analysing the {} branch of '{}' at index '{}'",
+            branch, self.path, index
+        )
+    }
+
+    pub fn error_context(&self, branch: &str, index: usize) -> String {
+        format!(
+            "executing the {} branch of '{}' at index '{}'",
+            branch, self.path, index
+        )
+    }
+}
+
+fn operands(condition: &Condition) -> Vec<&String> {
+    match condition {
+        Condition::Equals(left, right) | Condition::NotEquals(left, right) => vec![left, right],
+        Condition::Exists(value) | Condition::Truthy(value) => vec![value],
+        Condition::Not(condition) => operands(condition),
+    }
+}
+
+fn interpolate(value: &str, stack: &StackRef, ctx: &ContextRef) -> anyhow::Result<String> {
+    match Interpolateable::new(value) {
+        Some(interpolateable) => {
+            let mut target = String::new();
+            interpolateable.interpolate(stack, ctx, &mut target)?;
+            Ok(target)
+        }
+        None => Ok(value.into()),
+    }
+}
+
+fn is_truthy(value: &str) -> bool {
+    !matches!(
+        value.trim().to_lowercase().as_str(),
+        "" | "0" | "false" | "no"
+    )
+}
+
+fn evaluate(condition: &Condition, stack: &StackRef, ctx: &ContextRef) -> anyhow::Result<bool> {
+    Ok(match condition {
+        Condition::Equals(left, right) => {
+            interpolate(left, stack, ctx)? == interpolate(right, stack, ctx)?
+        }
+        Condition::NotEquals(left, right) => {
+            interpolate(left, stack, ctx)? != interpolate(right, stack, ctx)?
+        }
+        Condition::Exists(path) => directory::current(ctx)
+            .join(interpolate(path, stack, ctx)?)
+            .exists(),
+        Condition::Truthy(value) => is_truthy(&interpolate(value, stack, ctx)?),
+        Condition::Not(condition) => !evaluate(condition, stack, ctx)?,
+    })
+}
+
+impl Executor for ConditionalExecutor {
+    fn init(&mut self, stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        for operand in operands(&self.condition) {
+            if let Some(interpolateable) = Interpolateable::new(operand) {
+                interpolateable
+                    .assert_variables_allocated(&stack, &ctx, None)
+                    .with_span(&self.span, || "checking the condition")?;
+            }
+        }
+
+        let (then, otherwise) = match self.executeables.take() {
+            Some(executeables) => executeables,
+            None => return Err(ExecutorError::NotInitialized.into()),
+        };
+        let mut then = self.init_branch(then, &stack, &ctx, "then")?;
+        let mut otherwise = self.init_branch(otherwise, &stack, &ctx, "else")?;
+
+        // Only variables allocated on both branches are available after the if
+        let then_names: HashSet<String> = then.stack.borrow().allocated().cloned().collect();
+        let mut guaranteed: Vec<String> = otherwise
+            .stack
+            .borrow()
+            .allocated()
+            .filter(|name| then_names.contains(*name))
+            .cloned()
+            .collect();
+        guaranteed.sort();
+        for name in guaranteed {
+            stack.borrow_mut().allocate(name);
+        }
+
+        // A branch assigns the variables of the enclosing scopes as well instead of shadowing them,
+        // which are allocated in the parent stack to be carried over. Until they are set, reading
+        // them still falls through to the enclosing scopes.
+        for branch in [&mut then, &mut otherwise] {
+            let mut carried: Vec<String> = branch
+                .stack
+                .borrow()
+                .allocated()
+                .filter(|name| stack.borrow().assert_allocated(name).is_ok())
+                .cloned()
+                .collect();
+            carried.sort();
+            for name in &carried {
+                if !stack
+                    .borrow()
+                    .allocated()
+                    .any(|allocated| allocated == name)
+                {
+                    stack.borrow_mut().allocate(name.clone());
+                }
+            }
+            branch.carried = carried;
+        }
+
+        self.branches = Some((then, otherwise));
+
+        Ok(())
+    }
+
+    fn execute(&self, parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if let Some((then, otherwise)) = &self.branches {
+            let holds = evaluate(&self.condition, &parent_stack, &ctx)
+                .with_span(&self.span, || "evaluating the condition")?;
+            debug!("if {}: {}", self.condition, holds);
+
+            let (branch, name) = if holds {
+                (then, "then")
+            } else {
+                (otherwise, "else")
+            };
+            let branch_stack: StackRef = branch.stack.borrow().instantiate(&parent_stack).into();
+            for (counter, executor) in branch.executors.iter().enumerate() {
+                executor
+                    .execute(branch_stack.clone(), ctx.clone())
+                    .with_span(&self.span, || self.error_context(name, counter))?;
+            }
+
+            for variable in &branch.carried {
+                let value = branch_stack.borrow().get(variable)?;
+                parent_stack.borrow_mut().set(variable.clone(), value)?;
+            }
+
+            Ok(())
+        } else {
+            Err(ExecutorError::NotInitialized.into())
+        }
+    }
+}
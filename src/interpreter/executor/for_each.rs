@@ -0,0 +1,181 @@
+use log::debug;
+
+use crate::diagnostic::WithSpan;
+use crate::interpreter::context::ContextRef;
+use crate::interpreter::interpolateable::Interpolateable;
+use crate::interpreter::stack::StackRef;
+use crate::interpreter::variables::Variables;
+use crate::parse::ast::{Elements, Executeable, ExecuteableType, Span, Split, VariableBinding};
+
+use super::{child_path, get_executor, DynExecutor, Executor, ExecutorError, Stack};
+
+pub struct ForEachExecutor {
+    path: String,
+    variable: String,
+    elements: Elements,
+    variables: Variables,
+    /// The outputs as pairs of the variable in the parent stack and the collected one of the body
+    outputs: Vec<(String, String)>,
+    executeables: Vec<Executeable>,
+    executors: Vec<DynExecutor>,
+    stack: Option<StackRef>,
+    span: Span,
+}
+
+impl ForEachExecutor {
+    pub fn new(input: Executeable, path: String) -> anyhow::Result<Self> {
+        if let ExecuteableType::ForEach {
+            variable,
+            elements,
+            executeables,
+        } = input.executeable_type
+        {
+            let mut outputs = Vec::new();
+            for binding in input.output_variables.iter().flat_map(|val| &val.bindings) {
+                match binding {
+                    VariableBinding::Single(name) => outputs.push((name.clone(), name.clone())),
+                    VariableBinding::Dual(parent, child) => {
+                        outputs.push((parent.clone(), child.clone()))
+                    }
+                    other => {
+                        return Err(ExecutorError::InvalidLoopOutput(other.to_string()))
+                            .with_span(&input.span, || "checking the output variables")
+                    }
+                }
+            }
+
+            Ok(ForEachExecutor {
+                path,
+                variable,
+                elements,
+                variables: Variables::new(input.output_variables),
+                outputs,
+                executeables,
+                executors: Vec::new(),
+                stack: None,
+                span: input.span,
+            })
+        } else {
+            Err(ExecutorError::WrongExecutorType(input.executeable_type).into())
+        }
+    }
+
+    fn values(&self) -> Vec<&String> {
+        match &self.elements {
+            Elements::Split(value, _) => vec![value],
+            Elements::List(values) => values.iter().collect(),
+        }
+    }
+
+    /// Interpolates and splits the elements, where empty parts of a split value are skipped
+    fn resolve_elements(&self, stack: &StackRef, ctx: &ContextRef) -> anyhow::Result<Vec<String>> {
+        let mut values = Vec::new();
+        for value in self.values() {
+            values.push(match Interpolateable::new(value) {
+                Some(interpolateable) => {
+                    let mut target = String::new();
+                    interpolateable.interpolate(stack, ctx, &mut target)?;
+                    target
+                }
+                None => value.clone(),
+            });
+        }
+
+        let split = match &self.elements {
+            Elements::List(_) => return Ok(values),
+            Elements::Split(_, split) => split,
+        };
+        let value = values.remove(0);
+        let parts: Vec<&str> = match split {
+            Split::Lines => value.lines().collect(),
+            Split::Words => value.split_whitespace().collect(),
+            Split::Separator(separator) => value.split(separator.as_str()).collect(),
+        };
+        Ok(parts
+            .into_iter()
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    pub fn init_context(&self, index: usize) -> String {
+        format!("analysing loop '{}' at index '{}'", self.path, index)
+    }
+
+    pub fn error_context(&self, element: &str, index: usize) -> String {
+        format!(
+            "executing loop '{}' for '{}' at index '{}'",
+            self.path, element, index
+        )
+    }
+}
+
+impl Executor for ForEachExecutor {
+    fn init(&mut self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        for value in self.values() {
+            if let Some(interpolateable) = Interpolateable::new(value) {
+                interpolateable
+                    .assert_variables_allocated(&parent_stack, &ctx, None)
+                    .with_span(&self.span, || "checking the elements")?;
+            }
+        }
+
+        let mut child_stack: StackRef = Stack::inherit_new(&parent_stack).into();
+        child_stack.borrow_mut().allocate(self.variable.clone());
+
+        let executeables: Vec<Executeable> = self.executeables.drain(..).collect();
+        for (counter, executeable) in executeables.into_iter().enumerate() {
+            let path = child_path(&self.path, &executeable.name);
+            let mut executor = get_executor(executeable, path, child_stack.clone())?;
+            executor
+                .init(child_stack.clone(), ctx.clone())
+                .with_span(&self.span, || self.init_context(counter))?;
+            self.executors.push(executor);
+        }
+
+        self.variables
+            .allocate_and_check_all(&mut parent_stack, &mut child_stack)
+            .with_span(&self.span, || "checking the output variables")?;
+
+        self.stack = Some(child_stack);
+
+        Ok(())
+    }
+
+    fn execute(&self, parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if let Some(stack) = &self.stack {
+            let elements = self
+                .resolve_elements(&parent_stack, &ctx)
+                .with_span(&self.span, || "resolving the elements")?;
+            debug!("for {} in {} element(s)", self.variable, elements.len());
+
+            let mut collected: Vec<Vec<String>> = vec![Vec::new(); self.outputs.len()];
+            for element in &elements {
+                let child_stack: StackRef = stack.borrow().instantiate(&parent_stack).into();
+                child_stack
+                    .borrow_mut()
+                    .set(self.variable.clone(), element.clone())?;
+                for (counter, executor) in self.executors.iter().enumerate() {
+                    executor
+                        .execute(child_stack.clone(), ctx.clone())
+                        .with_span(&self.span, || self.error_context(element, counter))?;
+                }
+                for ((_, child), values) in self.outputs.iter().zip(collected.iter_mut()) {
+                    values.push(child_stack.borrow().get(child)?);
+                }
+            }
+
+            // Every output is joined by lines, which is empty if there was no element
+            for ((parent, _), values) in self.outputs.iter().zip(collected) {
+                parent_stack
+                    .borrow_mut()
+                    .set(parent.clone(), values.join("\n"))?;
+            }
+
+            Ok(())
+        } else {
+            Err(ExecutorError::NotInitialized.into())
+        }
+    }
+}
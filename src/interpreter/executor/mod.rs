@@ -1,20 +1,27 @@
 use thiserror::Error;
 
 use crate::interpreter::stack::Stack;
-use crate::parse::ast::{Executeable, ExecuteableType};
+use crate::parse::ast::{Executeable, ExecuteableType, VariableBinding, VariableBindings};
 use crate::runner::interface::RunnerInterfaceError;
+use crate::runner::message::result::Exit;
 
+use self::assignment::AssignmentExecutor;
 use self::block::BlockExecutor;
 use self::call::CallExecutor;
 use self::command::CommandExecutor;
+use self::conditional::ConditionalExecutor;
+use self::for_each::ForEachExecutor;
 use self::task::TaskExecutor;
 
 use super::context::ContextRef;
 use super::stack::StackRef;
 
+mod assignment;
 mod block;
 mod call;
 mod command;
+mod conditional;
+mod for_each;
 mod task;
 
 #[derive(Error, Debug)]
@@ -27,23 +34,193 @@ pub enum ExecutorError {
     NotInitialized,
     #[error("Error while calling the runner: {0}")]
     RunnerInterfaceError(RunnerInterfaceError),
+    #[error("Invalid value '{1}' for option '{0}'")]
+    InvalidOption(String, String),
+    #[error("'{0}' is not an option of a {1}")]
+    UnsupportedOption(String, String),
+    #[error("Command '{0}' failed with {1}")]
+    CommandFailed(String, Exit),
+    #[error("'{1}' does not declare the argument '{0}', it accepts: {2}")]
+    UnknownArgument(String, String, String),
+    #[error("'{0}' can not be an output of a loop, only variables of its body are collected")]
+    InvalidLoopOutput(String),
+    #[error("The {0} of an interactive command is not captured, only its 'status' can be bound")]
+    InteractiveOutput(String),
+    #[error("An interactive command reads from the terminal, so it can not be given a 'stdin'")]
+    InteractiveStdin,
+    #[error("An interactive command can not be given a 'timeout', as only the command itself and none of the processes it started could be terminated")]
+    InteractiveTimeout,
+    #[error("Interrupted by signal {0}")]
+    Interrupted(i32),
 }
 
+/// Executors allocate the variables of their scope in a stack at init, which only serves as a
+/// template that is instantiated for every execution, so no values are left from a previous one
 pub trait Executor {
     fn init(&mut self, stack: StackRef, ctx: ContextRef) -> anyhow::Result<()>;
 
-    fn execute(&mut self, stack: StackRef, ctx: ContextRef) -> anyhow::Result<()>;
+    /// Executes the initialized executor, which can be done any number of times
+    fn execute(&self, stack: StackRef, ctx: ContextRef) -> anyhow::Result<()>;
+}
+
+/// How the failed command that caused the error exited, if it was caused by one
+pub fn failed_exit(err: &anyhow::Error) -> Option<Exit> {
+    err.chain()
+        .find_map(|cause| match cause.downcast_ref::<ExecutorError>() {
+            Some(ExecutorError::CommandFailed(_, exit)) => Some(*exit),
+            _ => None,
+        })
+}
+
+/// Checks whether the error was caused by a command exiting with a not allowed status
+pub fn is_command_failure(err: &anyhow::Error) -> bool {
+    failed_exit(err).is_some()
+}
+
+/// The signal that interrupted the execution, if it was interrupted
+pub fn interrupted_by(err: &anyhow::Error) -> Option<i32> {
+    err.chain()
+        .find_map(|cause| match cause.downcast_ref::<ExecutorError>() {
+            Some(ExecutorError::Interrupted(signal)) => Some(*signal),
+            _ => None,
+        })
 }
 
 type DynExecutor = Box<dyn Executor>;
 
+/// A variable a task reads from its parent as argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclaredArgument {
+    pub name: String,
+    /// The value used if the argument is not passed, which makes it optional
+    pub default: Option<String>,
+}
+
+/// The options consumed by the executors themselves, which are no arguments of a task or call,
+/// except for `env` that is only an option if it nests the environment variables
+pub const OPTIONS: [&str; 6] = [
+    "timeout",
+    "cwd",
+    "env",
+    "retries",
+    "retry_delay",
+    "retry_on",
+];
+
+/// The name of the option the binding gives, if it is one
+fn option_name(binding: &VariableBinding) -> Option<&str> {
+    let name = binding.name();
+    let is_option = match binding {
+        VariableBinding::Nested(..) => OPTIONS.contains(&name),
+        _ => name != "env" && OPTIONS.contains(&name),
+    };
+    is_option.then_some(name)
+}
+
+/// The options without the ones consumed by the executors, which leaves the arguments
+pub fn without_options(options: Option<VariableBindings>) -> Option<VariableBindings> {
+    options.map(|options| VariableBindings {
+        bindings: options
+            .bindings
+            .into_iter()
+            .filter(|binding| option_name(binding).is_none())
+            .collect(),
+    })
+}
+
+/// The first of the executor options that is given but not supported by the executeable
+pub fn unsupported_option<'a>(
+    options: &'a Option<VariableBindings>,
+    supported: &[&str],
+) -> Option<&'a str> {
+    options
+        .iter()
+        .flat_map(|options| options.bindings.iter())
+        .filter_map(option_name)
+        .find(|name| !supported.contains(name))
+}
+
+pub fn declared_arguments(executeable: &Executeable) -> Vec<DeclaredArgument> {
+    match (
+        &executeable.executeable_type,
+        without_options(executeable.options.clone()),
+    ) {
+        (ExecuteableType::Task { .. }, Some(options)) => options
+            .bindings
+            .iter()
+            .filter_map(|binding| match binding {
+                VariableBinding::Single(name) | VariableBinding::Dual(_, name) => {
+                    Some(DeclaredArgument {
+                        name: name.clone(),
+                        default: None,
+                    })
+                }
+                VariableBinding::Default(name, default) => Some(DeclaredArgument {
+                    name: name.clone(),
+                    default: Some(default.clone()),
+                }),
+                VariableBinding::Literal(..) | VariableBinding::Nested(..) => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The fully qualified name of a child executeable, unnamed executeables (which got a generated
+/// numeric name) are referred to by the name of their parent
+fn child_path(parent_path: &str, name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_digit()) {
+        parent_path.into()
+    } else {
+        format!("{}.{}", parent_path, name)
+    }
+}
+
 #[allow(unreachable_patterns)]
-pub fn get_executor(input: Executeable, _stack: StackRef) -> anyhow::Result<DynExecutor> {
+/// Creates the executor, with `path` being the fully qualified name of the executeable
+pub fn get_executor(
+    input: Executeable,
+    path: String,
+    _stack: StackRef,
+) -> anyhow::Result<DynExecutor> {
     match &input.executeable_type {
-        ExecuteableType::Command { .. } => Ok(Box::new(CommandExecutor::new(input)?)),
-        ExecuteableType::Task { .. } => Ok(Box::new(TaskExecutor::new(input)?)),
-        ExecuteableType::Block { .. } => Ok(Box::new(BlockExecutor::new(input)?)),
-        ExecuteableType::Call { .. } => Ok(Box::new(CallExecutor::new(input)?)),
+        ExecuteableType::Command { .. } => Ok(Box::new(CommandExecutor::new(input, path)?)),
+        ExecuteableType::Task { .. } => Ok(Box::new(TaskExecutor::new(input, path)?)),
+        ExecuteableType::Block { .. } => Ok(Box::new(BlockExecutor::new(input, path)?)),
+        ExecuteableType::Call { .. } => Ok(Box::new(CallExecutor::new(input, path)?)),
+        ExecuteableType::Assignment { .. } => Ok(Box::new(AssignmentExecutor::new(input, path)?)),
+        ExecuteableType::Conditional { .. } => Ok(Box::new(ConditionalExecutor::new(input, path)?)),
+        ExecuteableType::ForEach { .. } => Ok(Box::new(ForEachExecutor::new(input, path)?)),
         exec_type => Err(ExecutorError::NotImplemented(exec_type.clone()).into()),
     }
 }
+
+/// Creates a stack with the constants of all namespaces enclosing the executeable at `path`
+pub fn namespace_scope(path: &str, ctx: &ContextRef) -> anyhow::Result<StackRef> {
+    let constants: Vec<Executeable> = ctx
+        .borrow()
+        .root_namespace
+        .constants(path)
+        .into_iter()
+        .cloned()
+        .collect();
+
+    let stack: StackRef = Stack::new().into();
+    for constant in constants {
+        let mut executor = AssignmentExecutor::new(constant, path.into())?;
+        executor.init(stack.clone(), ctx.clone())?;
+        executor.execute(stack.clone(), ctx.clone())?;
+    }
+    Ok(stack)
+}
+
+/// Formats the names as a sorted, quoted list for error messages
+pub fn quote_all<'a>(names: impl Iterator<Item = &'a String>) -> String {
+    let mut names: Vec<String> = names.map(|name| format!("'{}'", name)).collect();
+    names.sort();
+    if names.is_empty() {
+        "none".into()
+    } else {
+        names.join(", ")
+    }
+}
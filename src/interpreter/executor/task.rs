@@ -1,62 +1,118 @@
 use std::str;
+use std::time::Duration;
 
-use anyhow::Context;
 use log::{error, info};
 use thiserror::Error;
 
+use crate::diagnostic::{self, WithSpan};
 use crate::interpreter::context::ContextRef;
+use crate::interpreter::directory::{self, WorkingDirectory};
+use crate::interpreter::environment::{self, Environment};
+use crate::interpreter::retry::{self, RetryPolicy};
 use crate::interpreter::stack::StackRef;
+use crate::interpreter::timeout;
 use crate::interpreter::variables::Variables;
-use crate::parse::ast::{Executeable, ExecuteableType};
+use crate::parse::ast::{Executeable, ExecuteableType, Span};
 
-use super::{get_executor, DynExecutor, Executor, ExecutorError, Stack};
+use super::{
+    child_path, get_executor, namespace_scope, without_options, DynExecutor, Executor,
+    ExecutorError, Stack,
+};
 
 #[derive(Error, Debug)]
 pub enum TaskError {
     #[error("Task {0} is missing it's main")]
     MissingMain(String),
+    #[error("'let {0}' has to be placed before the stages of task {1}, as assignments are executed first")]
+    AssignmentAfterStage(String, String),
 }
 
+const STAGES: [&str; 4] = ["pre", "main", "post", "finally"];
+
 struct Executeables {
+    assignments: Vec<Executeable>,
     pre: Option<Executeable>,
     main: Executeable,
     post: Option<Executeable>,
+    finally: Option<Executeable>,
 }
 
 struct Executors {
+    assignments: Vec<DynExecutor>,
     pre: Option<DynExecutor>,
     main: DynExecutor,
     post: Option<DynExecutor>,
+    /// Executed after the other stages, even if they failed or were interrupted
+    finally: Option<DynExecutor>,
+    /// The stack inheriting from the namespace constants
     stack: StackRef,
 }
 
 pub struct TaskExecutor {
     name: String,
+    path: String,
     variables: Variables,
     arguments: Variables,
+    environment: Option<Environment>,
+    directory: Option<WorkingDirectory>,
+    retry: Option<RetryPolicy>,
+    timeout: Option<Duration>,
     executeables: Option<Executeables>,
     executors: Option<Executors>,
+    /// An assignment following a stage, which would be executed before it regardless
+    misplaced_assignment: Option<String>,
+    span: Span,
 }
 
 impl TaskExecutor {
-    pub fn new(input: Executeable) -> anyhow::Result<TaskExecutor> {
+    pub fn new(input: Executeable, path: String) -> anyhow::Result<TaskExecutor> {
         if let ExecuteableType::Task { executeables } = input.executeable_type {
             let pre_executeable = TaskExecutor::find_executeable(&executeables, "pre");
             let main_executeable = match TaskExecutor::find_executeable(&executeables, "main") {
                 Some(executeable) => executeable,
-                None => return Err(TaskError::MissingMain(input.name).into()),
+                None => {
+                    return Err(TaskError::MissingMain(input.name))
+                        .with_span(&input.span, || "defining the task")
+                }
             };
             let post_executeable = TaskExecutor::find_executeable(&executeables, "post");
+            let finally_executeable = TaskExecutor::find_executeable(&executeables, "finally");
+            let misplaced_assignment = TaskExecutor::find_misplaced_assignment(&executeables);
+            let assignments = executeables
+                .into_iter()
+                .filter(|executeable| {
+                    matches!(
+                        executeable.executeable_type,
+                        ExecuteableType::Assignment { .. }
+                    )
+                })
+                .collect();
+            let environment = Environment::from_options(&input.options)
+                .with_span(&input.span, || "defining the task")?;
+            let retry = RetryPolicy::from_options(&input.options)
+                .with_span(&input.span, || "defining the task")?;
+            let timeout = timeout::from_options(&input.options)
+                .with_span(&input.span, || "defining the task")?;
             Ok(TaskExecutor {
                 name: input.name,
+                path,
                 variables: Variables::new(input.output_variables),
-                arguments: Variables::new(input.options),
+                environment,
+                directory: WorkingDirectory::from_options(&input.options)
+                    .with_span(&input.span, || "defining the task")?,
+                retry,
+                timeout,
+                arguments: Variables::new(without_options(input.options)),
                 executeables: Some(Executeables {
+                    assignments,
                     pre: pre_executeable,
                     main: main_executeable,
                     post: post_executeable,
+                    finally: finally_executeable,
                 }),
                 executors: None,
+                misplaced_assignment,
+                span: input.span,
             })
         } else {
             Err(ExecutorError::WrongExecutorType(input.executeable_type).into())
@@ -73,17 +129,31 @@ impl TaskExecutor {
         None
     }
 
+    fn find_misplaced_assignment(executeables: &[Executeable]) -> Option<String> {
+        executeables
+            .iter()
+            .skip_while(|executeable| !STAGES.contains(&executeable.name.as_str()))
+            .find_map(|executeable| match &executeable.executeable_type {
+                ExecuteableType::Assignment { variable, .. } => Some(variable.clone()),
+                _ => None,
+            })
+    }
+
     pub fn error_context(&self, stage: &'static str) -> String {
         format!("executing task '{}' at stage '{}'", self.name, stage)
     }
 
     fn convert_and_init_executeable(
+        &self,
         stack: &mut StackRef,
         executeable: Option<Executeable>,
         ctx: ContextRef,
     ) -> anyhow::Result<Option<DynExecutor>> {
         let mut executor = match executeable {
-            Some(executeable) => get_executor(executeable, stack.clone())?,
+            Some(executeable) => {
+                let path = child_path(&self.path, &executeable.name);
+                get_executor(executeable, path, stack.clone())?
+            }
             None => return Ok(None),
         };
 
@@ -91,38 +161,176 @@ impl TaskExecutor {
 
         Ok(Some(executor))
     }
+
+    fn execute_stages(
+        &self,
+        executors: &Executors,
+        stack: &StackRef,
+        ctx: &ContextRef,
+    ) -> anyhow::Result<()> {
+        if let Some(pre) = &executors.pre {
+            pre.execute(stack.clone(), ctx.clone())
+                .with_span(&self.span, || self.error_context("executing_pre"))?;
+        }
+        executors
+            .main
+            .execute(stack.clone(), ctx.clone())
+            .with_span(&self.span, || self.error_context("executing_main"))?;
+
+        if let Some(post) = &executors.post {
+            post.execute(stack.clone(), ctx.clone())
+                .with_span(&self.span, || self.error_context("executing_post"))?;
+        }
+        Ok(())
+    }
+
+    /// Executes the finally stage after the result of the other stages, whose error takes precedence
+    /// over the one of the finally stage
+    fn execute_finally(
+        &self,
+        executors: &Executors,
+        stack: &StackRef,
+        ctx: &ContextRef,
+        result: anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let finally = match &executors.finally {
+            Some(finally) => finally,
+            None => return result,
+        };
+        let finally_result = finally
+            .execute(stack.clone(), ctx.clone())
+            .with_span(&self.span, || self.error_context("executing_finally"));
+        match (result, finally_result) {
+            (Err(err), Err(finally_err)) => {
+                error!(
+                    "The finally stage of task '{}' failed as well\n{}",
+                    self.name,
+                    diagnostic::render(&finally_err).trim_end()
+                );
+                Err(err)
+            }
+            (Err(err), Ok(())) | (Ok(()), Err(err)) => Err(err),
+            (Ok(()), Ok(())) => Ok(()),
+        }
+    }
+
+    /// Executes the task with a fresh stack, which is repeated for every attempt
+    fn execute_once(
+        &self,
+        executors: &Executors,
+        mut parent_stack: StackRef,
+        ctx: &ContextRef,
+    ) -> anyhow::Result<()> {
+        let mut child_stack: StackRef = executors.stack.borrow().fresh().into();
+        self.arguments
+            .carry_over(&mut child_stack, &mut parent_stack)
+            .with_span(&self.span, || self.error_context("get_args"))?;
+
+        info!("-> {}", &self.name);
+        for assignment in executors.assignments.iter() {
+            assignment
+                .execute(child_stack.clone(), ctx.clone())
+                .with_span(&self.span, || self.error_context("executing_assignments"))?;
+        }
+        let environment = environment::resolve(&self.environment, &child_stack)
+            .with_span(&self.span, || self.error_context("set_env"))?;
+        let directory = directory::resolve(&self.directory, &child_stack, ctx)
+            .with_span(&self.span, || self.error_context("set_cwd"))?;
+        environment::scoped(environment, ctx, || {
+            directory::scoped(directory, ctx, || {
+                let result = timeout::scoped(self.timeout, ctx, || {
+                    self.execute_stages(executors, &child_stack, ctx)
+                });
+                // The timeout of the task does not apply, as the cleanup has to happen regardless
+                self.execute_finally(executors, &child_stack, ctx, result)
+            })
+        })?;
+
+        self.variables
+            .carry_over(&mut parent_stack, &mut child_stack)?;
+        Ok(())
+    }
 }
 
 impl Executor for TaskExecutor {
     fn init(&mut self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if let Some(variable) = &self.misplaced_assignment {
+            return Err(TaskError::AssignmentAfterStage(
+                variable.clone(),
+                self.name.clone(),
+            ))
+            .with_span(&self.span, || self.error_context("check_assignments"));
+        }
         if let Some(executeables) = self.executeables.take() {
-            let mut child_stack: StackRef = Stack::new().into();
+            let scope = namespace_scope(&self.path, &ctx)
+                .with_span(&self.span, || self.error_context("init_constants"))?;
+            let mut child_stack: StackRef = Stack::inherit_new(&scope).into();
 
             self.arguments
                 .allocate_and_check_all(&mut child_stack, &mut parent_stack)
-                .with_context(|| self.error_context("check_args"))?;
-
-            let pre = Self::convert_and_init_executeable(
-                &mut child_stack,
-                executeables.pre,
-                ctx.clone(),
-            )?;
-            let mut main = get_executor(executeables.main, child_stack.clone())?;
-            main.init(child_stack.clone(), ctx.clone())?;
-            let post = Self::convert_and_init_executeable(
-                &mut child_stack,
-                executeables.post,
-                ctx.clone(),
-            )?;
+                .with_span(&self.span, || self.error_context("check_args"))?;
+
+            let mut assignments = Vec::new();
+            for executeable in executeables.assignments {
+                let mut executor =
+                    get_executor(executeable, self.path.clone(), child_stack.clone())?;
+                executor
+                    .init(child_stack.clone(), ctx.clone())
+                    .with_span(&self.span, || self.error_context("init_assignments"))?;
+                assignments.push(executor);
+            }
+
+            if let Some(environment) = &self.environment {
+                environment
+                    .assert_allocated(&child_stack)
+                    .with_span(&self.span, || self.error_context("check_env"))?;
+            }
+            if let Some(directory) = &self.directory {
+                directory
+                    .assert_allocated(&child_stack, &ctx)
+                    .with_span(&self.span, || self.error_context("check_cwd"))?;
+            }
+
+            let (pre, main, post, finally) =
+                environment::declared(&self.environment, &ctx, || {
+                    let pre = self
+                        .convert_and_init_executeable(
+                            &mut child_stack,
+                            executeables.pre,
+                            ctx.clone(),
+                        )
+                        .with_span(&self.span, || self.error_context("init_pre"))?;
+                    let main_path = child_path(&self.path, &executeables.main.name);
+                    let mut main = get_executor(executeables.main, main_path, child_stack.clone())?;
+                    main.init(child_stack.clone(), ctx.clone())
+                        .with_span(&self.span, || self.error_context("init_main"))?;
+                    let post = self
+                        .convert_and_init_executeable(
+                            &mut child_stack,
+                            executeables.post,
+                            ctx.clone(),
+                        )
+                        .with_span(&self.span, || self.error_context("init_post"))?;
+                    let finally = self
+                        .convert_and_init_executeable(
+                            &mut child_stack,
+                            executeables.finally,
+                            ctx.clone(),
+                        )
+                        .with_span(&self.span, || self.error_context("init_finally"))?;
+                    Ok((pre, main, post, finally))
+                })?;
 
             self.variables
                 .allocate_and_check_all(&mut parent_stack, &mut child_stack)
-                .with_context(|| self.error_context("check_vars"))?;
+                .with_span(&self.span, || self.error_context("check_vars"))?;
 
             self.executors = Some(Executors {
+                assignments,
                 pre,
                 main,
                 post,
+                finally,
                 stack: child_stack,
             });
 
@@ -132,30 +340,11 @@ impl Executor for TaskExecutor {
         }
     }
 
-    fn execute(&mut self, mut parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
-        if let Some(mut executors) = self.executors.take() {
-            self.arguments
-                .carry_over(&mut executors.stack, &mut parent_stack)
-                .with_context(|| self.error_context("get_args"))?;
-
-            info!("-> {}", &self.name);
-            if let Some(mut pre) = executors.pre {
-                pre.execute(executors.stack.clone(), ctx.clone())
-                    .with_context(|| self.error_context("executing_pre"))?;
-            }
-            executors
-                .main
-                .execute(executors.stack.clone(), ctx.clone())
-                .with_context(|| self.error_context("executing_main"))?;
-
-            if let Some(mut post) = executors.post {
-                post.execute(executors.stack.clone(), ctx.clone())
-                    .with_context(|| self.error_context("executing_post"))?;
-            }
-
-            self.variables
-                .carry_over(&mut parent_stack, &mut executors.stack)?;
-            Ok(())
+    fn execute(&self, parent_stack: StackRef, ctx: ContextRef) -> anyhow::Result<()> {
+        if let Some(executors) = &self.executors {
+            retry::retried(&self.retry, &self.path, &ctx, || {
+                self.execute_once(executors, parent_stack.clone(), &ctx)
+            })
         } else {
             Err(ExecutorError::NotInitialized.into())
         }
@@ -1,65 +1,178 @@
 use lazy_static::lazy_static;
-use regex::Regex;
+use regex::{Captures, Regex};
+use thiserror::Error;
 
+use crate::diagnostic::WithSpan;
+use crate::parse::ast::Span;
+
+use super::context::ContextRef;
 use super::stack::StackRef;
 
+#[derive(Error, Debug)]
+pub enum InterpolateableError {
+    #[error("environment variable '{0}' is not set, a default can be given like '${{env.{0}:-default}}'")]
+    EnvironmentVariableNotSet(String),
+}
+
 #[derive(Debug, PartialEq)]
 enum InterpolateableAfter {
     Other(Box<Interpolateable>),
     Value(String),
 }
 
+/// What is inserted in place of a `${...}`
+#[derive(Debug, PartialEq)]
+enum Reference {
+    /// `${name}`, a variable of the stack
+    Variable(String),
+    /// `${env.NAME}` or `${env.NAME:-default}`, a variable set by an enclosing `env` option or
+    /// otherwise of the environment `inst` was started with
+    Environment {
+        name: String,
+        default: Option<String>,
+    },
+}
+
+impl Reference {
+    fn from_capture(capture: &Captures) -> Self {
+        match capture.name("env_name") {
+            Some(name) => Reference::Environment {
+                name: name.as_str().into(),
+                default: capture.name("default").map(|val| val.as_str().into()),
+            },
+            None => Reference::Variable(capture["variable_name"].into()),
+        }
+    }
+
+    fn assert_available(&self, stack: &StackRef, ctx: &ContextRef) -> anyhow::Result<()> {
+        match self {
+            Reference::Variable(name) => stack.borrow().assert_allocated(name),
+            Reference::Environment {
+                default: Some(_), ..
+            } => Ok(()),
+            Reference::Environment {
+                name,
+                default: None,
+            } => {
+                let declared = ctx.borrow().environment_names.contains(name);
+                match declared || std::env::var(name).is_ok() {
+                    true => Ok(()),
+                    false => {
+                        Err(InterpolateableError::EnvironmentVariableNotSet(name.clone()).into())
+                    }
+                }
+            }
+        }
+    }
+
+    fn get(&self, stack: &StackRef, ctx: &ContextRef) -> anyhow::Result<String> {
+        match self {
+            Reference::Variable(name) => stack.borrow().get(name),
+            Reference::Environment { name, default } => {
+                match (environment_variable(name, ctx), default) {
+                    (Ok(value), _) => Ok(value),
+                    (Err(_), Some(default)) => Ok(default.clone()),
+                    (Err(_), None) => {
+                        Err(InterpolateableError::EnvironmentVariableNotSet(name.clone()).into())
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The value of the enclosing `env` options, where inner ones take precedence, or of the process
+fn environment_variable(name: &str, ctx: &ContextRef) -> Result<String, std::env::VarError> {
+    let ctx = ctx.borrow();
+    match ctx.environment.iter().rev().find(|(key, _)| key == name) {
+        Some((_, value)) => Ok(value.clone()),
+        None => std::env::var(name),
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Interpolateable {
     before: String,
     after: InterpolateableAfter,
-    variable_name: String,
+    reference: Reference,
+    /// Offset of the reference (including the `${`) in the whole interpolated value
+    offset: usize,
+    /// Length of the reference including the `${` and `}`
+    length: usize,
 }
 
 impl Interpolateable {
     pub fn new(value: &str) -> Option<Self> {
+        Self::new_at(value, 0)
+    }
+
+    fn new_at(value: &str, offset: usize) -> Option<Self> {
         lazy_static! {
-            static ref RE: Regex = Regex::new(r"\$\{(?P<variable_name>(\w|_)+)\}").unwrap();
+            static ref RE: Regex = Regex::new(
+                r"\$\{(env\.(?P<env_name>\w+)(:-(?P<default>[^}]*))?|(?P<variable_name>(\w|_)+))\}"
+            )
+            .unwrap();
         }
         match RE.captures(value) {
             Some(capture) => {
-                let variable_name = capture.name("variable_name").unwrap();
+                let reference = capture.get(0).unwrap();
 
-                // Mask out ${}
-                let before_variable = &value[..variable_name.start() - 2];
-                let after_variable = &value[variable_name.end() + 1..];
+                let before_reference = &value[..reference.start()];
+                let after_reference = &value[reference.end()..];
 
-                let next = Interpolateable::new(after_variable);
+                let next = Interpolateable::new_at(after_reference, offset + reference.end());
                 let after = match next {
                     Some(other) => InterpolateableAfter::Other(Box::new(other)),
-                    None => InterpolateableAfter::Value(after_variable.into()),
+                    None => InterpolateableAfter::Value(after_reference.into()),
                 };
 
                 Some(Self {
-                    before: before_variable.into(),
+                    before: before_reference.into(),
                     after,
-                    variable_name: variable_name.as_str().into(),
+                    reference: Reference::from_capture(&capture),
+                    offset: offset + reference.start(),
+                    length: reference.end() - reference.start(),
                 })
             }
             None => None,
         }
     }
 
-    pub fn assert_variables_allocated(&self, stack: &StackRef) -> anyhow::Result<()> {
-        stack.borrow_mut().assert_allocated(&self.variable_name)?;
+    /// Checks all variables and environment variables without a default, with `span` being the
+    /// location of the whole interpolated value if it is written verbatim in the source
+    pub fn assert_variables_allocated(
+        &self,
+        stack: &StackRef,
+        ctx: &ContextRef,
+        span: Option<&Span>,
+    ) -> anyhow::Result<()> {
+        let available = self.reference.assert_available(stack, ctx);
+        match span {
+            Some(span) => {
+                available.with_span(&span.narrow(self.offset, self.length), || "accessed here")?
+            }
+            None => available?,
+        }
 
         match &self.after {
             InterpolateableAfter::Value(_) => Ok(()),
-            InterpolateableAfter::Other(other) => other.assert_variables_allocated(stack),
+            InterpolateableAfter::Other(other) => {
+                other.assert_variables_allocated(stack, ctx, span)
+            }
         }
     }
 
-    pub fn interpolate(&self, stack: &StackRef, target: &mut String) -> anyhow::Result<()> {
+    pub fn interpolate(
+        &self,
+        stack: &StackRef,
+        ctx: &ContextRef,
+        target: &mut String,
+    ) -> anyhow::Result<()> {
         target.push_str(&self.before);
-        target.push_str(&stack.borrow().get(&self.variable_name)?);
+        target.push_str(&self.reference.get(stack, ctx)?);
 
         match &self.after {
-            InterpolateableAfter::Other(other) => other.interpolate(stack, target)?,
+            InterpolateableAfter::Other(other) => other.interpolate(stack, ctx, target)?,
             InterpolateableAfter::Value(after) => target.push_str(after),
         }
 
@@ -69,9 +182,17 @@ impl Interpolateable {
 
 #[cfg(test)]
 mod tests {
-    use crate::interpreter::stack::Stack;
+    use crate::interpreter::context::{Context, ContextRef};
+    use crate::interpreter::stack::{Stack, StackRef};
+    use crate::interpreter::RootNamespace;
+    use crate::util::channel::TwoWayChannel;
+
+    use super::{Interpolateable, InterpolateableError};
 
-    use super::Interpolateable;
+    fn get_ctx() -> ContextRef {
+        let (runner_requester, _) = TwoWayChannel::new_pair();
+        Context::new(RootNamespace::new(), runner_requester).into()
+    }
 
     #[test]
     fn empty() {
@@ -101,7 +222,7 @@ mod tests {
 
         let mut output = String::new();
         interpolateable
-            .interpolate(&stack.into(), &mut output)
+            .interpolate(&stack.into(), &get_ctx(), &mut output)
             .unwrap();
 
         assert_eq!(&output, "value_with_test-value");
@@ -117,7 +238,7 @@ mod tests {
 
         let mut output = String::new();
         interpolateable
-            .interpolate(&stack.into(), &mut output)
+            .interpolate(&stack.into(), &get_ctx(), &mut output)
             .unwrap();
 
         assert_eq!(&output, "value_with_val_variable");
@@ -133,9 +254,59 @@ mod tests {
 
         let mut output = String::new();
         interpolateable
-            .interpolate(&stack.into(), &mut output)
+            .interpolate(&stack.into(), &get_ctx(), &mut output)
             .unwrap();
 
         assert_eq!(&output, "value_with_val1_multiple_val2_variable");
     }
+
+    #[test]
+    fn environment_variable() {
+        std::env::set_var("INST_TEST_INTERPOLATE", "from-env");
+        let interpolateable = Interpolateable::new("${var}-${env.INST_TEST_INTERPOLATE}").unwrap();
+
+        let stack: StackRef = Stack::from(vec![("var", "val")]).into();
+        interpolateable
+            .assert_variables_allocated(&stack, &get_ctx(), None)
+            .unwrap();
+
+        let mut output = String::new();
+        interpolateable
+            .interpolate(&stack, &get_ctx(), &mut output)
+            .unwrap();
+
+        assert_eq!(&output, "val-from-env");
+    }
+
+    #[test]
+    fn environment_variable_default() {
+        let interpolateable =
+            Interpolateable::new("${env.INST_TEST_INTERPOLATE_MISSING:-fallback}/bin").unwrap();
+
+        let stack: StackRef = Stack::new().into();
+        interpolateable
+            .assert_variables_allocated(&stack, &get_ctx(), None)
+            .unwrap();
+
+        let mut output = String::new();
+        interpolateable
+            .interpolate(&stack, &get_ctx(), &mut output)
+            .unwrap();
+
+        assert_eq!(&output, "fallback/bin");
+    }
+
+    #[test]
+    fn environment_variable_missing() {
+        let interpolateable = Interpolateable::new("${env.INST_TEST_INTERPOLATE_MISSING}").unwrap();
+
+        let err = interpolateable
+            .assert_variables_allocated(&Stack::new().into(), &get_ctx(), None)
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<InterpolateableError>(),
+            Some(InterpolateableError::EnvironmentVariableNotSet(name)) if name == "INST_TEST_INTERPOLATE_MISSING"
+        ));
+    }
 }
@@ -1,37 +1,61 @@
-use std::thread::{self, JoinHandle};
+use std::{
+    collections::HashMap,
+    thread::{self, JoinHandle},
+};
 
 use thiserror::Error;
 
+use crate::diagnostic::WithSpan;
+use crate::parse::ast::Span;
 use crate::TaskLangError;
 
 pub use self::namespace::RootNamespace;
 use self::{
     context::{Context, ContextRef, RunnerRequester},
-    executor::{get_executor, Executor},
+    executor::{
+        declared_arguments, get_executor, interrupted_by, is_command_failure, namespace_scope,
+        quote_all, DeclaredArgument, Executor,
+    },
     stack::{Stack, StackRef},
 };
 
 mod context;
+mod directory;
+mod duration;
+mod environment;
 mod executor;
 mod interpolateable;
 mod namespace;
+mod retry;
 mod stack;
+mod timeout;
 mod variables;
 
 #[derive(Error, Debug)]
 pub enum InterpreterError {
     #[error("Interpreter is in invalid state")]
     InvalidState,
+    #[error("unknown argument(s) {0}, the task accepts: {1}")]
+    UnknownArguments(String, String),
+    #[error("missing argument(s) {0}")]
+    MissingArguments(String),
 }
 
 struct ExecutionUnit {
+    task_name: String,
+    /// Whether the executor was initialized, after which it can be executed any number of times
+    initialized: bool,
     stack: StackRef,
     executor: Box<dyn Executor>,
+    /// The names of the arguments the executeable reads from the root stack
+    arguments: Vec<DeclaredArgument>,
+    span: Span,
 }
 
 pub struct Interpreter {
     root_namespace: RootNamespace,
     execution_unit: Option<ExecutionUnit>,
+    arguments: HashMap<String, String>,
     ctx: ContextRef,
 }
 
@@ -41,42 +65,167 @@ impl Interpreter {
         Self {
             root_namespace: root,
             execution_unit: None,
+            arguments: HashMap::new(),
             ctx: Context::new(root_clone, runner_requester).into(),
         }
     }
 
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.ctx.borrow_mut().dry_run = dry_run;
+    }
+
+    /// Sets the arguments the task is run with, which are passed to it from the root stack
+    pub fn set_arguments(&mut self, arguments: HashMap<String, String>) {
+        self.arguments = arguments;
+    }
+
     pub fn resolve(&mut self, task_name: &str) -> anyhow::Result<()> {
         let executeable = self.root_namespace.resolve_name(task_name)?;
 
-        let stack: StackRef = Stack::new().into();
-        let executor = get_executor(executeable.clone(), stack.clone())?;
+        let directory = self.root_namespace.working_directory(task_name).into();
+        self.ctx.borrow_mut().directories = vec![directory];
+
+        let scope = namespace_scope(task_name, &self.ctx)?;
+        let stack: StackRef = Stack::inherit_new(&scope).into();
+        let executor = get_executor(executeable.clone(), task_name.into(), stack.clone())?;
+
+        self.execution_unit = Some(ExecutionUnit {
+            task_name: task_name.into(),
+            initialized: false,
+            stack,
+            executor,
+            arguments: declared_arguments(executeable),
+            span: executeable.span.clone(),
+        });
 
-        self.execution_unit = Some(ExecutionUnit { stack, executor });
+        Ok(())
+    }
+
+    /// Allocates the declared arguments in the root stack and sets them to the given values, which
+    /// have to match the declared ones, except for optional ones. Without values the required
+    /// arguments are only allocated.
+    pub fn bind_arguments(
+        &mut self,
+        values: Option<&HashMap<String, String>>,
+    ) -> anyhow::Result<()> {
+        let unit = match &self.execution_unit {
+            Some(unit) => unit,
+            None => return Err(InterpreterError::InvalidState.into()),
+        };
+
+        if let Some(values) = values {
+            let unknown = values.keys().filter(|name| {
+                !unit
+                    .arguments
+                    .iter()
+                    .any(|declared| &&declared.name == name)
+            });
+            if unknown.clone().next().is_some() {
+                return Err(InterpreterError::UnknownArguments(
+                    quote_all(unknown),
+                    quote_all(unit.arguments.iter().map(|declared| &declared.name)),
+                ))
+                .with_span(&unit.span, || "declaring the arguments");
+            }
+            let missing = unit
+                .arguments
+                .iter()
+                .filter(|declared| declared.default.is_none())
+                .map(|declared| &declared.name)
+                .filter(|name| !values.contains_key(*name));
+            if missing.clone().next().is_some() {
+                return Err(InterpreterError::MissingArguments(quote_all(missing)))
+                    .with_span(&unit.span, || "declaring the arguments");
+            }
+        }
+
+        let mut stack = unit.stack.borrow_mut();
+        for declared in &unit.arguments {
+            match values.and_then(|values| values.get(&declared.name)) {
+                Some(value) => {
+                    stack.allocate(declared.name.clone());
+                    stack.set(declared.name.clone(), value.clone())?;
+                }
+                // Optional arguments fall back to their default when not allocated
+                None if declared.default.is_none() => stack.allocate(declared.name.clone()),
+                None => (),
+            }
+        }
 
         Ok(())
     }
 
     pub fn initialize(&mut self) -> anyhow::Result<()> {
         match &mut self.execution_unit {
-            Some(unit) => unit.executor.init(unit.stack.clone(), self.ctx.clone()),
+            Some(unit) => {
+                unit.executor.init(unit.stack.clone(), self.ctx.clone())?;
+                unit.initialized = true;
+                Ok(())
+            }
             None => Err(InterpreterError::InvalidState.into()),
         }
     }
 
-    pub fn execute(&mut self) -> anyhow::Result<()> {
+    pub fn execute(&self) -> anyhow::Result<()> {
+        match &self.execution_unit {
+            Some(unit) if unit.initialized => {
+                unit.executor.execute(unit.stack.clone(), self.ctx.clone())
+            }
+            _ => Err(InterpreterError::InvalidState.into()),
+        }
+    }
+
+    /// Whether the task was already resolved and initialized, so it can be executed again
+    fn is_initialized(&self, task_name: &str) -> bool {
+        matches!(&self.execution_unit, Some(unit) if unit.initialized && unit.task_name == task_name)
+    }
+
+    /// Replaces the root stack by a fresh one, so the values of a previous run are not visible to
+    /// the next one
+    fn reset_stack(&mut self) -> anyhow::Result<()> {
         match &mut self.execution_unit {
-            Some(unit) => unit.executor.execute(unit.stack.clone(), self.ctx.clone()),
+            Some(unit) => {
+                let stack = unit.stack.borrow().fresh();
+                unit.stack = stack.into();
+                Ok(())
+            }
             None => Err(InterpreterError::InvalidState.into()),
         }
     }
 
-    pub fn run(&mut self, task_name: &str) -> anyhow::Result<()> {
+    /// Resolves and statically analyses the task without executing it, assuming all arguments are given
+    pub fn check(&mut self, task_name: &str) -> Result<(), TaskLangError> {
         self.resolve(task_name)
             .map_err(|err| TaskLangError::ResolveError(task_name.into(), err))?;
-        self.initialize()
-            .map_err(|err| TaskLangError::StaticAnalysisError(task_name.into(), err))?;
-        self.execute()
-            .map_err(|err| TaskLangError::ExecutionError(task_name.into(), err))?;
+        self.bind_arguments(None)
+            .and_then(|_| self.initialize())
+            .map_err(|err| TaskLangError::StaticAnalysisError(task_name.into(), err))
+    }
+
+    /// Runs the task, where a task that was already run is executed again without being resolved
+    /// and analysed anew
+    pub fn run(&mut self, task_name: &str) -> Result<(), TaskLangError> {
+        let arguments = self.arguments.clone();
+        if self.is_initialized(task_name) {
+            self.reset_stack()
+                .and_then(|_| self.bind_arguments(Some(&arguments)))
+                .map_err(|err| TaskLangError::StaticAnalysisError(task_name.into(), err))?;
+        } else {
+            self.resolve(task_name)
+                .map_err(|err| TaskLangError::ResolveError(task_name.into(), err))?;
+            self.bind_arguments(Some(&arguments))
+                .and_then(|_| self.initialize())
+                .map_err(|err| TaskLangError::StaticAnalysisError(task_name.into(), err))?;
+        }
+        self.execute().map_err(|err| {
+            if let Some(signal) = interrupted_by(&err) {
+                TaskLangError::InterruptedError(task_name.into(), signal, err)
+            } else if is_command_failure(&err) {
+                TaskLangError::CommandFailedError(task_name.into(), err)
+            } else {
+                TaskLangError::ExecutionError(task_name.into(), err)
+            }
+        })?;
         Ok(())
     }
 
@@ -84,10 +233,752 @@ impl Interpreter {
         root_namespace: RootNamespace,
         runner_requester: RunnerRequester,
         task_name: String,
-    ) -> JoinHandle<()> {
+        arguments: HashMap<String, String>,
+        dry_run: bool,
+    ) -> JoinHandle<Result<(), TaskLangError>> {
         thread::spawn(move || {
             let mut interpreter = Interpreter::new(root_namespace, runner_requester);
-            interpreter.run(&task_name).unwrap();
+            interpreter.set_arguments(arguments);
+            interpreter.set_dry_run(dry_run);
+            interpreter.run(&task_name)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tempfile::TempDir;
+
+    use crate::util::{channel::TwoWayChannel, interrupt::Interrupt};
+
+    use super::*;
+
+    fn get_interpreter(module: &str) -> Interpreter {
+        let mut root_namespace = RootNamespace::new();
+        root_namespace
+            .add_root(crate::parse::parse("test.inst", module.into()).unwrap())
+            .unwrap();
+        let (runner_requester, _) = TwoWayChannel::new_pair();
+        Interpreter::new(root_namespace, runner_requester)
+    }
+
+    #[test]
+    fn ok_dry_run_placeholders() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nlet (out: stdout) from task as x: {\n  let (pre: stdout) from run as pre: echo pre;\n  let (stdout) from run as main: echo ${pre};\n};",
+        );
+        interpreter.set_dry_run(true);
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(
+            stack.borrow().get("out").unwrap(),
+            "<stdout of test.x.main>"
+        );
+    }
+
+    #[test]
+    fn ok_dry_run_unnamed_uses_parent() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nlet (out: var) from block as x: {\n  let (var: stdout) from run: echo;\n};",
+        );
+        interpreter.set_dry_run(true);
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("out").unwrap(), "<stdout of test.x>");
+    }
+
+    fn get_arguments(arguments: &[(&str, &str)]) -> HashMap<String, String> {
+        arguments
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn get_static_analysis_error(result: Result<(), TaskLangError>) -> String {
+        match result {
+            Err(TaskLangError::StaticAnalysisError(_, err)) => err.root_cause().to_string(),
+            other => panic!("expected a static analysis error, got {:?}", other),
+        }
+    }
+
+    const ARGUMENTS_MODULE: &str = "module as test;\nlet (out: stdout) from task with (env, region: target_region) as x: {\n  let (stdout) from run as main: echo ${env} ${region};\n};";
+
+    #[test]
+    fn ok_arguments() {
+        let mut interpreter = get_interpreter(ARGUMENTS_MODULE);
+        interpreter.set_dry_run(true);
+        interpreter.set_arguments(get_arguments(&[("env", "prod"), ("target_region", "eu")]));
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("env").unwrap(), "prod");
+        assert_eq!(stack.borrow().get("target_region").unwrap(), "eu");
+    }
+
+    #[test]
+    fn ok_check_without_arguments() {
+        let mut interpreter = get_interpreter(ARGUMENTS_MODULE);
+
+        assert!(interpreter.check("test.x").is_ok());
+    }
+
+    #[test]
+    fn nok_missing_arguments() {
+        let mut interpreter = get_interpreter(ARGUMENTS_MODULE);
+        interpreter.set_dry_run(true);
+        interpreter.set_arguments(get_arguments(&[("env", "prod")]));
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.run("test.x")),
+            "missing argument(s) 'target_region'"
+        );
+    }
+
+    #[test]
+    fn nok_unknown_arguments() {
+        let mut interpreter = get_interpreter(ARGUMENTS_MODULE);
+        interpreter.set_dry_run(true);
+        interpreter.set_arguments(get_arguments(&[
+            ("env", "prod"),
+            ("region", "eu"),
+            ("target_region", "eu"),
+        ]));
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.run("test.x")),
+            "unknown argument(s) 'region', the task accepts: 'env', 'target_region'"
+        );
+    }
+
+    const CALL_MODULE: &str = "module as test;\nlet (out: stdout) from task with (env, region: target_region) as deploy: {\n  let (stdout) from run as main: echo ${env} ${region};\n};";
+
+    #[test]
+    fn ok_call_arguments() {
+        let mut interpreter = get_interpreter(&format!(
+            "{}\nlet (result: out) from task as x: {{\n  let (target_env: stdout) from run as pre: echo;\n  let (out) from call with (env: target_env, target_region: \"eu\") as main: test.deploy;\n}};",
+            CALL_MODULE
+        ));
+        interpreter.set_dry_run(true);
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(
+            stack.borrow().get("result").unwrap(),
+            "<stdout of test.deploy.main>"
+        );
+    }
+
+    #[test]
+    fn nok_call_unknown_argument() {
+        let mut interpreter = get_interpreter(&format!(
+            "{}\ntask with (env) as x: {{\n  call with (env, region: env) as main: test.deploy;\n}};",
+            CALL_MODULE
+        ));
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "'test.deploy' does not declare the argument 'region', it accepts: 'env', 'target_region'"
+        );
+    }
+
+    #[test]
+    fn nok_call_undefined_argument() {
+        let mut interpreter = get_interpreter(&format!(
+            "{}\ntask as x: {{\n  call with (env: undefined, target_region: \"eu\") as main: test.deploy;\n}};",
+            CALL_MODULE
+        ));
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "tried to access undefined variable 'undefined'"
+        );
+    }
+
+    const DEFAULTS_MODULE: &str = "module as test;\nlet (used_env: env) from task with (env = \"staging\", region) as deploy: {\n  run as main: echo ${env} ${region};\n};";
+
+    fn get_used_env(mut interpreter: Interpreter, task_name: &str) -> String {
+        interpreter.set_dry_run(true);
+        interpreter.run(task_name).unwrap();
+        let stack = interpreter.execution_unit.unwrap().stack;
+        let used_env = stack.borrow().get("used_env").unwrap();
+        used_env
+    }
+
+    #[test]
+    fn ok_default_argument_omitted() {
+        let mut interpreter = get_interpreter(DEFAULTS_MODULE);
+        interpreter.set_arguments(get_arguments(&[("region", "eu")]));
+
+        assert_eq!(get_used_env(interpreter, "test.deploy"), "staging");
+    }
+
+    #[test]
+    fn ok_default_argument_overridden() {
+        let mut interpreter = get_interpreter(DEFAULTS_MODULE);
+        interpreter.set_arguments(get_arguments(&[("env", "prod"), ("region", "eu")]));
+
+        assert_eq!(get_used_env(interpreter, "test.deploy"), "prod");
+    }
+
+    #[test]
+    fn ok_rerun_with_other_arguments() {
+        let mut interpreter = get_interpreter(DEFAULTS_MODULE);
+        interpreter.set_dry_run(true);
+        let used_env = |interpreter: &Interpreter| {
+            let stack = interpreter.execution_unit.as_ref().unwrap().stack.clone();
+            let used_env = stack.borrow().get("used_env").unwrap();
+            used_env
+        };
+
+        interpreter.set_arguments(get_arguments(&[("env", "prod"), ("region", "eu")]));
+        interpreter.run("test.deploy").unwrap();
+        assert_eq!(used_env(&interpreter), "prod");
+
+        // The default applies again, as the root stack is not reused
+        interpreter.set_arguments(get_arguments(&[("region", "us")]));
+        interpreter.run("test.deploy").unwrap();
+        assert_eq!(used_env(&interpreter), "staging");
+    }
+
+    #[test]
+    fn ok_call_executed_repeatedly() {
+        let module = format!(
+            "{}\nlet (used: used_env) from for env in (\"dev\", \"prod\") as x: {{\n  let (used_env) from call with (env, region: \"eu\"): test.deploy;\n}};",
+            DEFAULTS_MODULE
+        );
+        let mut interpreter = get_interpreter(&module);
+        interpreter.set_dry_run(true);
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("used").unwrap(), "dev\nprod");
+    }
+
+    #[test]
+    fn ok_default_argument_call() {
+        let module = format!(
+            "{}\nlet (used_env) from task as x: {{\n  let (used_env) from call with (region: \"eu\") as main: test.deploy;\n}};\nlet (used_env) from task as y: {{\n  let (used_env) from call with (env: \"prod\", region: \"eu\") as main: test.deploy;\n}};",
+            DEFAULTS_MODULE
+        );
+
+        assert_eq!(get_used_env(get_interpreter(&module), "test.x"), "staging");
+        assert_eq!(get_used_env(get_interpreter(&module), "test.y"), "prod");
+    }
+
+    #[test]
+    fn ok_default_argument_not_inherited() {
+        let module = format!(
+            "{}\nlet env = \"constant\";\nlet (used_env) from task as x: {{\n  let env = \"outer\";\n  let (used_env) from call with (region: \"eu\") as main: test.deploy;\n}};",
+            DEFAULTS_MODULE
+        );
+
+        assert_eq!(get_used_env(get_interpreter(&module), "test.x"), "staging");
+    }
+
+    #[test]
+    fn nok_default_argument_required_missing() {
+        let mut interpreter = get_interpreter(DEFAULTS_MODULE);
+        interpreter.set_arguments(get_arguments(&[("env", "prod")]));
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.run("test.deploy")),
+            "missing argument(s) 'region'"
+        );
+    }
+
+    #[test]
+    fn nok_assignment_after_stage() {
+        let mut interpreter = get_interpreter(
+            "module as test;\ntask as x: {\n  run as main: echo ${value};\n  let value = \"late\";\n};",
+        );
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "'let value' has to be placed before the stages of task x, as assignments are executed first"
+        );
+    }
+
+    #[test]
+    fn ok_assignments() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nlet region = \"eu\";\ncollection as c: {\n  let bucket = \"b-${region}\";\n  let (out: value) from task as x: {\n    let value = \"${bucket}\";\n    let (value) from block as main: {\n      let value = \"${value}/${region}\";\n    };\n  };\n};",
+        );
+
+        interpreter.run("test.c.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("out").unwrap(), "b-eu/eu");
+    }
+
+    #[test]
+    fn nok_assignment_undefined_variable() {
+        let mut interpreter = get_interpreter(
+            "module as test;\ntask as x: {\n  let value = \"${undefined}\";\n  run as main: echo;\n};",
+        );
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "tried to access undefined variable 'undefined'"
+        );
+    }
+
+    /// An interpreter that actually executes the commands with a default command runner
+    fn get_executing_interpreter(module: &str) -> Interpreter {
+        let mut root_namespace = RootNamespace::new();
+        root_namespace
+            .add_root(crate::parse::parse("test.inst", module.into()).unwrap())
+            .unwrap();
+        get_executing_interpreter_for(root_namespace)
+    }
+
+    fn get_executing_interpreter_for(root_namespace: RootNamespace) -> Interpreter {
+        let (runner_requester, runner_responder) = TwoWayChannel::new_pair();
+        crate::runner::server::RunnerServer::new_thread(runner_responder);
+        crate::runner::interface::RunnerInterface::new(runner_requester.clone())
+            .create("default".into(), "command".into(), HashMap::new())
+            .unwrap();
+        Interpreter::new(root_namespace, runner_requester)
+    }
+
+    #[test]
+    fn ok_environment() {
+        let mut interpreter = get_executing_interpreter(
+            "module as test;\nlet (scoped: stdout, after: outside) from task with (env: (GREETING: greeting, TARGET: \"world\")) as x: {\n  let greeting = \"hello\";\n  let (stdout, outside) from block as main: {\n    let (stdout) from block with (env: (TARGET: \"block\")) as inner: {\n      let (stdout) from run with (shell, trim_stdout, env: (EXTRA: \"!\")) as cmd: echo \"$GREETING $TARGET$EXTRA\";\n    };\n    let (outside: stdout) from run with (shell, trim_stdout) as after: echo \"$TARGET$EXTRA\";\n  };\n};",
+        );
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("scoped").unwrap(), "hello block!");
+        assert_eq!(stack.borrow().get("after").unwrap(), "world");
+    }
+
+    #[test]
+    fn nok_environment_undefined_variable() {
+        let mut interpreter =
+            get_interpreter("module as test;\nrun with (env: (KEY: undefined)) as x: echo;");
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "tried to access undefined variable 'undefined'"
+        );
+    }
+
+    #[test]
+    fn ok_environment_variable_of_enclosing_option() {
+        let mut interpreter = get_executing_interpreter(
+            "module as test;\nlet (block_value, task_value) from task with (env: (INST_TEST_SCOPED: \"task\")) as x: {\n  let (block_value) from block with (env: (INST_TEST_SCOPED: \"block\")) as pre: {\n    let (block_value: stdout) from run with (trim_stdout): echo ${env.INST_TEST_SCOPED};\n  };\n  let (task_value: stdout) from run with (trim_stdout) as main: echo ${env.INST_TEST_SCOPED};\n};",
+        );
+
+        interpreter.check("test.x").unwrap();
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("block_value").unwrap(), "block");
+        assert_eq!(stack.borrow().get("task_value").unwrap(), "task");
+    }
+
+    #[test]
+    fn nok_environment_variable_not_set() {
+        let mut interpreter = get_interpreter(
+            "module as test;\ntask as x: {\n  let home = \"${env.INST_TEST_NOT_SET}\";\n  run as main: echo ${env.INST_TEST_NOT_SET_EITHER:-default};\n};",
+        );
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "environment variable 'INST_TEST_NOT_SET' is not set, a default can be given like '${env.INST_TEST_NOT_SET:-default}'"
+        );
+    }
+
+    #[test]
+    fn ok_working_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        std::fs::create_dir_all(base.join("first/sub")).unwrap();
+        std::fs::create_dir_all(base.join("second")).unwrap();
+
+        let mut root_namespace = RootNamespace::new();
+        for (module, content) in [
+            ("first", "module as first;\nlet (task_dir: stdout, called_dir: called, absolute_dir: absolute) from task with (cwd: \"sub\") as x: {\n  let (stdout) from run with (trim_stdout) as pre: pwd;\n  let (called: stdout) from call as main: second.y;\n  let (absolute: stdout) from run with (trim_stdout, cwd: \"/\") as post: pwd;\n};"),
+            ("second", "module as second;\nlet (stdout) from run with (trim_stdout) as y: pwd;"),
+        ] {
+            root_namespace
+                .add_root(crate::parse::parse("test.inst", content.into()).unwrap())
+                .unwrap();
+            root_namespace.set_working_directory(module, base.join(module));
+        }
+        let mut interpreter = get_executing_interpreter_for(root_namespace);
+
+        interpreter.run("first.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        let get = |name| PathBuf::from(stack.borrow().get(name).unwrap());
+        assert_eq!(get("task_dir"), base.join("first/sub"));
+        assert_eq!(get("called_dir"), base.join("second"));
+        assert_eq!(get("absolute_dir"), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn ok_working_directory_variable() {
+        let mut interpreter = get_executing_interpreter(
+            "module as test;\nlet (task_dir, run_dir) from task with (dir = \"/\", cwd: dir) as x: {\n  let sub = \"tmp\";\n  let (task_dir: stdout) from run with (trim_stdout) as pre: pwd;\n  let (run_dir: stdout) from run with (trim_stdout, cwd: sub) as main: pwd;\n};",
+        );
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("task_dir").unwrap(), "/");
+        assert_eq!(stack.borrow().get("run_dir").unwrap(), "/tmp");
+    }
+
+    #[test]
+    fn nok_working_directory_variable_undefined() {
+        let mut interpreter =
+            get_interpreter("module as test;\nrun with (cwd: missing) as x: pwd;");
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "tried to access undefined variable 'missing'"
+        );
+    }
+
+    /// A module counting the attempts in a file of a fresh directory, which succeeds from the given
+    /// attempt on and is removed once the returned directory is dropped
+    fn get_retry_module(options: &str, succeeding_attempt: u32) -> (TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        let module = format!(
+            "module as test;\nlet (attempts: stdout, status) from run with (shell, trim_stdout, cwd: \"{}\", {}) as x: echo x >> attempts && wc -l < attempts && test $(wc -l < attempts) -ge {};\nlet (attempts) from task with ({}) as y: {{\n  let (attempts: stdout) from run with (cwd: \"{}\", shell) as main: echo x >> attempts && test $(wc -l < attempts) -ge {} && wc -l < attempts;\n}};\nlet (attempts) from task as z: {{\n  let (attempts) from call with ({}) as main: test.y;\n}};",
+            base.display(),
+            options,
+            succeeding_attempt,
+            options,
+            base.display(),
+            succeeding_attempt,
+            options,
+        );
+        (dir, module)
+    }
+
+    #[test]
+    fn ok_retries() {
+        let (_dir, module) = get_retry_module("retries: 2", 3);
+        let mut interpreter = get_executing_interpreter(&module);
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("attempts").unwrap(), "3");
+        assert_eq!(stack.borrow().get("status").unwrap(), "0");
+    }
+
+    #[test]
+    fn ok_retries_task_and_call() {
+        let (_dir, module) = get_retry_module("retries: 3, retry_delay: 10ms", 2);
+        let mut interpreter = get_executing_interpreter(&module);
+        interpreter.run("test.y").unwrap();
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("attempts").unwrap().trim(), "2");
+
+        let (_dir, module) = get_retry_module("retries: 3", 2);
+        let mut interpreter = get_executing_interpreter(&module);
+        interpreter.run("test.z").unwrap();
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("attempts").unwrap().trim(), "2");
+    }
+
+    #[test]
+    fn ok_retries_exhausted_binds_final_attempt() {
+        let (_dir, module) = get_retry_module("retries: 1", 5);
+        let mut interpreter = get_executing_interpreter(&module);
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("attempts").unwrap(), "2");
+        assert_eq!(stack.borrow().get("status").unwrap(), "1");
+    }
+
+    #[test]
+    fn nok_retries_other_status() {
+        let (_dir, module) = get_retry_module("retries: 3, retry_on: \"75\"", 2);
+        let mut interpreter = get_executing_interpreter(&module);
+
+        assert!(matches!(
+            interpreter.run("test.y"),
+            Err(TaskLangError::CommandFailedError(..))
+        ));
+    }
+
+    #[test]
+    fn nok_retry_delay_interrupted() {
+        let (_dir, module) = get_retry_module("retries: 3, retry_delay: 1h", 5);
+        let mut interpreter = get_executing_interpreter(&module);
+        let interrupt = Interrupt::default();
+        interpreter.ctx.borrow_mut().interrupt = interrupt.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            interrupt.trigger(libc::SIGTERM);
+        });
+
+        assert!(matches!(
+            interpreter.run("test.x"),
+            Err(TaskLangError::InterruptedError(_, libc::SIGTERM, _))
+        ));
+    }
+
+    #[test]
+    fn nok_timeout() {
+        let mut interpreter = get_executing_interpreter(
+            "module as test;\nlet (status) from task with (timeout: 100ms) as x: {\n  let (status) from run with (timeout: 10s) as main: sleep 5;\n};\ntask with (timeout: 10s) as y: {\n  run with (timeout: 100ms) as main: sleep 5;\n};",
+        );
+
+        interpreter.run("test.x").unwrap();
+        let stack = interpreter.execution_unit.as_ref().unwrap().stack.clone();
+        assert_eq!(stack.borrow().get("status").unwrap(), "124");
+
+        assert!(matches!(
+            interpreter.run("test.y"),
+            Err(TaskLangError::CommandFailedError(..))
+        ));
+    }
+
+    #[test]
+    fn ok_timeout_unbounded() {
+        let mut interpreter = get_executing_interpreter(
+            "module as test;\ntask with (timeout: 18446744073709551615s) as x: {\n  run with (timeout: 18446744073709551615s) as main: true;\n};",
+        );
+
+        interpreter.run("test.x").unwrap();
+    }
+
+    #[test]
+    fn nok_options_not_in_scope() {
+        for name in ["timeout", "cwd", "retries"] {
+            let mut interpreter = get_interpreter(&format!(
+                "module as test;\ntask with (timeout: 1s, cwd: \"/tmp\", retries: 1) as x: {{\n  run as main: echo ${{{}}};\n}};",
+                name
+            ));
+
+            assert_eq!(
+                get_static_analysis_error(interpreter.check("test.x")),
+                format!("tried to access undefined variable '{}'", name)
+            );
+        }
+    }
+
+    #[test]
+    fn nok_call_unsupported_option() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nrun as x: true;\ntask as y: {\n  call with (timeout: 1s) as main: test.x;\n};",
+        );
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.y")),
+            "'timeout' is not an option of a call"
+        );
+    }
+
+    #[test]
+    fn nok_timeout_invalid() {
+        let mut interpreter =
+            get_interpreter("module as test;\nrun with (timeout: 5d) as x: sleep 5;");
+
+        assert!(matches!(
+            interpreter.check("test.x"),
+            Err(TaskLangError::ResolveError(..))
+        ));
+    }
+
+    #[test]
+    fn ok_signal_and_binary_output() {
+        let mut interpreter = get_executing_interpreter(
+            "module as test;\nlet (status, lossy, raw, after) from task as x: {\n  let (status) from run with (shell) as pre: kill -9 $$;\n  let (lossy, raw) from block as main: {\n    let (lossy: stdout) from run with (shell): printf 'a\\377';\n    let (raw: stdout) from run with (shell, encoding: \"raw\"): printf 'a\\377';\n  };\n  let (after: stdout) from run with (trim_stdout) as post: echo after;\n};",
+        );
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("status").unwrap(), "137");
+        assert_eq!(stack.borrow().get("lossy").unwrap(), "a\u{FFFD}");
+        assert_eq!(stack.borrow().get("raw").unwrap(), "a\u{ff}");
+        assert_eq!(stack.borrow().get("after").unwrap(), "after");
+    }
+
+    #[test]
+    fn ok_finally_after_failure_and_interrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        let mut interpreter = get_executing_interpreter(&format!(
+            "module as test;\ntask with (cwd: \"{0}\") as x: {{\n  run as main: false;\n  run as finally: touch failed;\n}};\ntask with (cwd: \"{0}\") as y: {{\n  run as pre: touch pre;\n  run as main: touch main;\n  run as finally: touch interrupted;\n}};",
+            base.display()
+        ));
+
+        assert!(matches!(
+            interpreter.run("test.x"),
+            Err(TaskLangError::CommandFailedError(..))
+        ));
+        assert!(base.join("failed").exists());
+
+        let interrupt = Interrupt::default();
+        interrupt.trigger(libc::SIGINT);
+        interpreter.ctx.borrow_mut().interrupt = interrupt;
+        let err = interpreter.run("test.y").unwrap_err();
+        assert!(matches!(
+            err,
+            TaskLangError::InterruptedError(_, libc::SIGINT, _)
+        ));
+        assert_eq!(err.exit_code(), 130);
+        assert!(!base.join("pre").exists());
+        assert!(!base.join("main").exists());
+        assert!(base.join("interrupted").exists());
+    }
+
+    #[test]
+    fn ok_interactive_status() {
+        let mut interpreter = get_executing_interpreter(
+            "module as test;\nlet (status) from run with (interactive, shell) as x: exit 3;",
+        );
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("status").unwrap(), "3");
+    }
+
+    #[test]
+    fn nok_interactive_output() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nlet (out: stdout) from run with (interactive) as x: psql;",
+        );
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "The stdout of an interactive command is not captured, only its 'status' can be bound"
+        );
+    }
+
+    #[test]
+    fn nok_interactive_stdin() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nblock as x: {\n  let input = \"a\";\n  run with (interactive, stdin: input): vim -;\n};",
+        );
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "An interactive command reads from the terminal, so it can not be given a 'stdin'"
+        );
+    }
+
+    #[test]
+    fn nok_interactive_timeout() {
+        let mut interpreter =
+            get_interpreter("module as test;\nrun with (interactive, timeout: 1m) as x: ssh host;");
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "An interactive command can not be given a 'timeout', as only the command itself and none of the processes it started could be terminated"
+        );
+    }
+
+    #[test]
+    fn ok_conditional() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nlet (equals, truthy, exists) from block as x: {\n  let value = \"no\";\n  let (status) from run: false;\n  if ${status} == \"0\": {\n    let equals = \"then\";\n  } else: {\n    let equals = \"else\";\n  };\n  if ${value}: {\n    let truthy = \"then\";\n  } else: {\n    let truthy = \"else\";\n  };\n  if !exists(\"/not/existing\"): {\n    let exists = \"then\";\n  } else: {\n    let (exists: stdout) from run: echo;\n  };\n};",
+        );
+        interpreter.set_dry_run(true);
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("equals").unwrap(), "then");
+        assert_eq!(stack.borrow().get("truthy").unwrap(), "else");
+        assert_eq!(stack.borrow().get("exists").unwrap(), "then");
+    }
+
+    #[test]
+    fn ok_conditional_reassigns_outer_variable() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nlet region = \"eu\";\nlet (out, nested, untouched, region) from task as x: {\n  let out = \"before\";\n  let nested = \"before\";\n  let untouched = \"before\";\n  let (out, nested, untouched, region) from block as main: {\n    if \"a\" == \"a\": {\n      let out = \"then\";\n      let region = \"us\";\n      if \"b\" == \"b\": {\n        let nested = \"${out}-inner\";\n      };\n    };\n    if \"a\" == \"b\": {\n      let untouched = \"then\";\n    };\n  };\n};",
+        );
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("out").unwrap(), "then");
+        assert_eq!(stack.borrow().get("nested").unwrap(), "then-inner");
+        assert_eq!(stack.borrow().get("untouched").unwrap(), "before");
+        assert_eq!(stack.borrow().get("region").unwrap(), "us");
+    }
+
+    #[test]
+    fn nok_conditional_one_branch() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nblock as x: {\n  if \"a\" != \"b\": {\n    let value = \"a\";\n    let other = \"b\";\n  } else: {\n    let other = \"c\";\n  };\n  run: echo ${other} ${value};\n};",
+        );
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "tried to access undefined variable 'value'"
+        );
+    }
+
+    #[test]
+    fn nok_conditional_undefined_variable() {
+        let mut interpreter =
+            get_interpreter("module as test;\nif ${undefined} == \"a\" as x: {\n  run: echo;\n};");
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "tried to access undefined variable 'undefined'"
+        );
+    }
+
+    #[test]
+    fn ok_for_each() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nlet (lines, words, list, empty) from block as x: {\n  let value = \"a b\\nc\";\n  let (lines: line) from for item in ${value}: {\n    let line = \"<${item}>\";\n  };\n  let (words: word) from for item in ${value} split words: {\n    let word = \"${item}\";\n  };\n  let (list: entry) from for item in (\"x\", ${value}): {\n    let entry = \"${item}!\";\n  };\n  let (empty: entry) from for item in \"\" split \",\": {\n    let entry = \"${item}\";\n  };\n};",
+        );
+        interpreter.set_dry_run(true);
+
+        interpreter.run("test.x").unwrap();
+
+        let stack = interpreter.execution_unit.unwrap().stack;
+        assert_eq!(stack.borrow().get("lines").unwrap(), "<a b>\n<c>");
+        assert_eq!(stack.borrow().get("words").unwrap(), "a\nb\nc");
+        assert_eq!(stack.borrow().get("list").unwrap(), "x!\na b\nc!");
+        assert_eq!(stack.borrow().get("empty").unwrap(), "");
+    }
+
+    #[test]
+    fn nok_for_each_variable_after_loop() {
+        let mut interpreter = get_interpreter(
+            "module as test;\nblock as x: {\n  for item in \"a\": {\n    run: echo ${item};\n  };\n  run: echo ${item};\n};",
+        );
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "tried to access undefined variable 'item'"
+        );
+    }
+
+    #[test]
+    fn nok_for_each_undefined_variable() {
+        let mut interpreter =
+            get_interpreter("module as test;\nfor item in ${undefined} as x: {\n  run: echo;\n};");
+
+        assert_eq!(
+            get_static_analysis_error(interpreter.check("test.x")),
+            "tried to access undefined variable 'undefined'"
+        );
+    }
+}
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 use thiserror::Error;
 
+use crate::diagnostic::WithSpan;
 use crate::parse::ast::{Executeable, Namespace, NamespaceOrExecuteable};
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -28,8 +30,8 @@ impl<'a> NamespaceResolver<'a> {
     }
 
     pub fn resolve(&self, name_parts: &[&str]) -> anyhow::Result<&'a Executeable> {
-        let current_part: &str = match name_parts.get(0) {
-            Some(val) => *val,
+        let current_part: &str = match name_parts.first() {
+            Some(val) => val,
             None => return Err(NamespaceError::NotAExecuteable(self.namespace.name.clone()).into()),
         };
 
@@ -42,7 +44,7 @@ impl<'a> NamespaceResolver<'a> {
         }
 
         let next_part: &str = match name_parts.get(1) {
-            Some(val) => *val,
+            Some(val) => val,
             None => return Err(NamespaceError::NotAExecuteable(self.namespace.name.clone()).into()),
         };
 
@@ -76,18 +78,21 @@ pub enum RootNamespaceError {
 #[derive(Clone)]
 pub struct RootNamespace {
     namespaces: HashMap<String, Namespace>,
+    working_directories: HashMap<String, PathBuf>,
 }
 
 impl RootNamespace {
     pub fn new() -> RootNamespace {
         RootNamespace {
             namespaces: HashMap::new(),
+            working_directories: HashMap::new(),
         }
     }
 
     pub fn add_root(&mut self, namespace: Namespace) -> anyhow::Result<()> {
         if self.namespaces.contains_key(&namespace.name) {
-            return Err(RootNamespaceError::ModuleNameAlreadyUsed(namespace.name).into());
+            return Err(RootNamespaceError::ModuleNameAlreadyUsed(namespace.name))
+                .with_span(&namespace.span, || "defining the module");
         }
         assert!(self
             .namespaces
@@ -97,13 +102,79 @@ impl RootNamespace {
         Ok(())
     }
 
+    /// Sets the directory the commands of the module are executed in
+    pub fn set_working_directory(&mut self, module: &str, directory: PathBuf) {
+        self.working_directories.insert(module.into(), directory);
+    }
+
+    /// The working directory of the module of the executeable with the fully qualified name, which
+    /// is the directory `inst` was started in if none was set
+    pub fn working_directory(&self, path: &str) -> &Path {
+        let module = path.split('.').next().unwrap_or_default();
+        match self.working_directories.get(module) {
+            Some(directory) => directory,
+            None => Path::new("."),
+        }
+    }
+
+    /// All modules, ordered by their name
+    pub fn modules(&self) -> Vec<&Namespace> {
+        let mut modules: Vec<&Namespace> = self.namespaces.values().collect();
+        modules.sort_by(|a, b| a.name.cmp(&b.name));
+        modules
+    }
+
+    /// The fully qualified names of all executeables that can be resolved, ordered by name
+    pub fn executeable_names(&self) -> Vec<String> {
+        fn collect(namespace: &Namespace, prefix: &str, names: &mut Vec<String>) {
+            for child in namespace.children.values() {
+                let name = format!("{}.{}", prefix, child.get_name());
+                match child {
+                    NamespaceOrExecuteable::Namespace(namespace) => {
+                        collect(namespace, &name, names)
+                    }
+                    NamespaceOrExecuteable::Executeable(_) => names.push(name),
+                }
+            }
+        }
+
+        let mut names = Vec::new();
+        for namespace in self.namespaces.values() {
+            collect(namespace, &namespace.name, &mut names);
+        }
+        names.sort();
+        names
+    }
+
+    /// The constants of all namespaces enclosing the executeable with the fully qualified name, ordered
+    /// from the module to the innermost collection
+    pub fn constants(&self, path: &str) -> Vec<&Executeable> {
+        let mut parts = path.split('.');
+        let mut namespace = match parts.next().and_then(|name| self.namespaces.get(name)) {
+            Some(namespace) => namespace,
+            None => return Vec::new(),
+        };
+
+        let mut constants: Vec<&Executeable> = namespace.constants.iter().collect();
+        for part in parts {
+            match namespace.children.get(part) {
+                Some(NamespaceOrExecuteable::Namespace(child)) => {
+                    namespace = child;
+                    constants.extend(namespace.constants.iter());
+                }
+                _ => break,
+            }
+        }
+        constants
+    }
+
     pub fn resolve_name(&self, target_name: &str) -> anyhow::Result<&Executeable> {
         let target_name_vec: Vec<&str> = target_name.split('.').collect();
         self.resolve(&target_name_vec)
     }
 
     pub fn resolve(&self, name_parts: &[&str]) -> anyhow::Result<&Executeable> {
-        if let Some(module_name) = name_parts.get(0) {
+        if let Some(module_name) = name_parts.first() {
             match self.namespaces.get(*module_name) {
                 Some(namespace) => NamespaceResolver::new(namespace).resolve(name_parts),
                 None => Err(RootNamespaceError::ModuleNotFound((*module_name).into()).into()),
@@ -128,8 +199,9 @@ mod tests {
     use crate::parse::ast::Namespace;
     use crate::parse::ast::NamespaceOrExecuteable;
     use crate::parse::ast::NamespaceType;
+    use crate::parse::ast::Span;
 
-    use super::NamespaceResolver;
+    use super::{NamespaceResolver, RootNamespace};
 
     fn get_collection(name: &'static str, mut children: Vec<NamespaceOrExecuteable>) -> Namespace {
         Namespace {
@@ -139,6 +211,8 @@ mod tests {
                 .drain(..)
                 .map(|val| (val.get_name().to_owned(), val))
                 .collect(),
+            constants: Vec::new(),
+            span: Span::default(),
         }
     }
 
@@ -148,10 +222,11 @@ mod tests {
             name: name.into(),
             options: None,
             executeable_type: ExecuteableType::Command { cmd: "".into() },
+            span: Span::default(),
         })
     }
 
-    fn split(value: &'static str) -> Vec<&str> {
+    fn split(value: &'static str) -> Vec<&'static str> {
         value.split(".").collect()
     }
 
@@ -332,4 +407,29 @@ mod tests {
             NamespaceError::NotFound("task".into(), "root".into())
         );
     }
+
+    #[test]
+    fn ok_executeable_names() {
+        let mut root_namespace = RootNamespace::new();
+        root_namespace
+            .add_root(get_collection(
+                "root",
+                vec![
+                    get_executeable("task"),
+                    NamespaceOrExecuteable::Namespace(get_collection(
+                        "collection",
+                        vec![get_executeable("b"), get_executeable("a")],
+                    )),
+                ],
+            ))
+            .unwrap();
+        root_namespace
+            .add_root(get_collection("other", vec![]))
+            .unwrap();
+
+        assert_eq!(
+            root_namespace.executeable_names(),
+            vec!["root.collection.a", "root.collection.b", "root.task"]
+        );
+    }
 }
@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use log::warn;
+
+use crate::parse::ast::VariableBindings;
+use crate::runner::message::result::Exit;
+use crate::util::interrupt::Interrupt;
+
+use super::context::ContextRef;
+use super::duration;
+use super::executor::{failed_exit, ExecutorError};
+
+/// The options configuring the retry policy
+pub const OPTIONS: [&str; 3] = ["retries", "retry_delay", "retry_on"];
+
+/// How often a failing executeable is attempted again, given by the options
+/// `retries: 3, retry_delay: 5s, retry_on: "1,75"`
+pub struct RetryPolicy {
+    retries: u32,
+    delay: Duration,
+    /// The statuses a failure is retried on, any failure is retried if not given
+    retry_on: Option<Vec<i32>>,
+}
+
+impl RetryPolicy {
+    pub fn from_options(options: &Option<VariableBindings>) -> anyhow::Result<Option<Self>> {
+        let options = match options {
+            Some(options) => options,
+            None => return Ok(None),
+        };
+        let retries = match options.find("retries") {
+            Some(retries) => retries
+                .parse()
+                .map_err(|_| ExecutorError::InvalidOption("retries".into(), retries.into()))?,
+            None => return Ok(None),
+        };
+        let delay = match options.find("retry_delay") {
+            Some(delay) => duration::parse(delay)
+                .ok_or_else(|| ExecutorError::InvalidOption("retry_delay".into(), delay.into()))?,
+            None => Duration::ZERO,
+        };
+        let retry_on = match options.find("retry_on") {
+            Some(retry_on) => Some(
+                retry_on
+                    .split(',')
+                    .map(|status| {
+                        status.trim().parse().map_err(|_| {
+                            ExecutorError::InvalidOption("retry_on".into(), retry_on.into())
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            None => None,
+        };
+
+        Ok(Some(Self {
+            retries,
+            delay,
+            retry_on,
+        }))
+    }
+
+    /// Whether the attempt, counting from 1, that failed with the exit is retried
+    pub fn retries(&self, attempt: u32, exit: &Exit) -> bool {
+        attempt <= self.retries
+            && match &self.retry_on {
+                Some(retry_on) => exit
+                    .status()
+                    .parse()
+                    .map(|status| retry_on.contains(&status))
+                    .unwrap_or(false),
+                None => true,
+            }
+    }
+
+    /// Logs the failed attempt and waits for the delay before the next one, which is cut short by a
+    /// received signal
+    pub fn wait(&self, path: &str, attempt: u32, exit: &Exit, interrupt: &Interrupt) {
+        warn!(
+            "'{}' failed with {} on attempt {} of {}, retrying in {:?}",
+            path,
+            exit,
+            attempt,
+            self.retries + 1,
+            self.delay
+        );
+        interrupt.sleep(self.delay);
+    }
+}
+
+/// Executes `f` until it succeeds, where only command failures matching the policy are retried
+pub fn retried<T>(
+    policy: &Option<RetryPolicy>,
+    path: &str,
+    ctx: &ContextRef,
+    f: impl FnMut() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    retried_on(policy, path, ctx, f, |result| match result {
+        Ok(_) => None,
+        Err(err) => failed_exit(err),
+    })
+}
+
+/// Executes `f` as long as `failure` gives the exit of a failed attempt matching the policy
+pub fn retried_on<T>(
+    policy: &Option<RetryPolicy>,
+    path: &str,
+    ctx: &ContextRef,
+    mut f: impl FnMut() -> anyhow::Result<T>,
+    failure: impl Fn(&anyhow::Result<T>) -> Option<Exit>,
+) -> anyhow::Result<T> {
+    let policy = match policy {
+        Some(policy) => policy,
+        None => return f(),
+    };
+    let interrupt = ctx.borrow().interrupt.clone();
+
+    let mut attempt = 1;
+    loop {
+        let result = f();
+        match failure(&result) {
+            Some(exit) if policy.retries(attempt, &exit) => {
+                policy.wait(path, attempt, &exit, &interrupt);
+                attempt += 1;
+            }
+            _ => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::ast::VariableBinding;
+
+    use super::*;
+
+    fn get_policy(options: &[(&str, &str)]) -> RetryPolicy {
+        let bindings = options
+            .iter()
+            .map(|(name, value)| VariableBinding::Literal(name.to_string(), value.to_string()))
+            .collect();
+        RetryPolicy::from_options(&Some(VariableBindings { bindings }))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn ok_retries() {
+        let policy = get_policy(&[("retries", "2")]);
+        assert!(policy.retries(1, &Exit::Code(1)));
+        assert!(policy.retries(2, &Exit::Signal(9)));
+        assert!(!policy.retries(3, &Exit::Code(1)));
+    }
+
+    #[test]
+    fn ok_retry_on() {
+        let policy = get_policy(&[
+            ("retries", "3"),
+            ("retry_delay", "5s"),
+            ("retry_on", "1,75"),
+        ]);
+        assert_eq!(policy.delay, Duration::from_secs(5));
+        assert!(policy.retries(1, &Exit::Code(75)));
+        assert!(!policy.retries(1, &Exit::Code(2)));
+    }
+
+    #[test]
+    fn nok_invalid() {
+        let options = |name: &str, value: &str| {
+            Some(VariableBindings {
+                bindings: vec![
+                    VariableBinding::Literal(name.into(), value.into()),
+                    VariableBinding::Literal("retries".into(), "1".into()),
+                ],
+            })
+        };
+        assert!(RetryPolicy::from_options(&options("retries", "many")).is_err());
+        assert!(RetryPolicy::from_options(&options("retry_delay", "5d")).is_err());
+        assert!(RetryPolicy::from_options(&options("retry_on", "1,x")).is_err());
+    }
+}
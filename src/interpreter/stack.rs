@@ -35,6 +35,31 @@ impl Stack {
         }
     }
 
+    /// Creates a stack inheriting from the parent with the same variables allocated as this one, but
+    /// none of them set, which allows to execute an initialized executor with a fresh stack
+    pub fn instantiate(&self, parent: &StackRef) -> Stack {
+        let mut stack = Stack::inherit_new(parent);
+        stack.variables = self
+            .variables
+            .keys()
+            .map(|name| (name.clone(), None))
+            .collect();
+        stack
+    }
+
+    /// Like `instantiate`, but inheriting from the same parent as this stack
+    pub fn fresh(&self) -> Stack {
+        Self {
+            variables: self
+                .variables
+                .keys()
+                .map(|name| (name.clone(), None))
+                .collect(),
+            parent: self.parent.clone(),
+            height: self.height,
+        }
+    }
+
     pub fn get(&self, name: &str) -> anyhow::Result<String> {
         trace!("Getting '{}' from stack {}@{:p}", name, self.height, self);
         match self.variables.get(name) {
@@ -46,25 +71,19 @@ impl Stack {
         }
     }
 
+    /// The value set in this stack itself, ignoring the ones of the enclosing stacks
+    pub fn get_own(&self, name: &str) -> Option<String> {
+        self.variables.get(name).cloned().flatten()
+    }
+
     pub fn set(&mut self, name: String, value: String) -> anyhow::Result<()> {
-        if value.len() > 10 {
-            trace!(
-                "Setting '{}' to {:?}..{:?}' for stack {}@{:p}",
-                &name,
-                &value[..10],
-                &value[value.len() - 10..],
-                self.height,
-                self
-            );
-        } else {
-            trace!(
-                "Setting '{}' to {:?} for stack {}@{:p}",
-                &name,
-                &value,
-                self.height,
-                self
-            );
-        }
+        trace!(
+            "Setting '{}' to {} for stack {}@{:p}",
+            &name,
+            abbreviated(&value),
+            self.height,
+            self
+        );
         if self.variables.insert(name.clone(), Some(value)).is_none() {
             return Err(Error::UnallocatedVariableAccessed(name).into());
         }
@@ -81,6 +100,11 @@ impl Stack {
         self.variables.insert(name, None);
     }
 
+    /// The names of the variables allocated in this stack, without the ones of its parents
+    pub fn allocated(&self) -> impl Iterator<Item = &String> {
+        self.variables.keys()
+    }
+
     pub fn assert_allocated(&self, name: &str) -> anyhow::Result<()> {
         trace!(
             "Asserting allocation '{}' for stack {}@{:p}",
@@ -122,3 +146,28 @@ impl From<Stack> for StackRef {
         Rc::new(RefCell::new(stack))
     }
 }
+
+/// The value quoted, with only its first and last 10 characters if it is longer
+fn abbreviated(value: &str) -> String {
+    let count = value.chars().count();
+    if count <= 10 {
+        return format!("{:?}", value);
+    }
+    let start: String = value.chars().take(10).collect();
+    let end: String = value.chars().skip(count - 10).collect();
+    format!("{:?}..{:?}", start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ok_abbreviated() {
+        assert_eq!(abbreviated("short"), "\"short\"");
+        assert_eq!(
+            abbreviated("ääääääääääöüüüüüüüüüü"),
+            "\"ääääääääää\"..\"üüüüüüüüüü\""
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+use crate::parse::ast::VariableBindings;
+
+use super::context::ContextRef;
+use super::duration;
+use super::executor::ExecutorError;
+
+/// The time an executeable may take, given by the option `timeout: 30s`
+pub fn from_options(options: &Option<VariableBindings>) -> anyhow::Result<Option<Duration>> {
+    match options.as_ref().and_then(|options| options.find("timeout")) {
+        Some(timeout) => match duration::parse(timeout) {
+            Some(timeout) => Ok(Some(timeout)),
+            None => Err(ExecutorError::InvalidOption("timeout".into(), timeout.into()).into()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Runs `f` with a deadline after the timeout, which every command executed within it is bound by
+pub fn scoped<T>(
+    timeout: Option<Duration>,
+    ctx: &ContextRef,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    // A deadline too far in the future to be represented is no deadline at all
+    let deadline = match timeout.and_then(|timeout| Instant::now().checked_add(timeout)) {
+        Some(deadline) => deadline,
+        None => return f(),
+    };
+
+    ctx.borrow_mut().deadlines.push(deadline);
+    let result = f();
+    ctx.borrow_mut().deadlines.pop();
+    result
+}
+
+/// The time a command may take, which is its own timeout limited by the deadlines of the enclosing
+/// tasks
+pub fn remaining(timeout: Option<Duration>, ctx: &ContextRef) -> Option<Duration> {
+    let now = Instant::now();
+    ctx.borrow()
+        .deadlines
+        .iter()
+        .map(|deadline| deadline.saturating_duration_since(now))
+        .chain(timeout)
+        .min()
+}
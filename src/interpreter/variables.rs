@@ -13,6 +13,33 @@ impl Variables {
         Self { bindings }
     }
 
+    /// The names the bindings allocate in the to_stack
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.bindings
+            .iter()
+            .flat_map(|bindings| bindings.bindings.iter())
+            .filter_map(|binding| match binding {
+                VariableBinding::Single(name)
+                | VariableBinding::Dual(name, _)
+                | VariableBinding::Literal(name, _)
+                | VariableBinding::Default(name, _) => Some(name),
+                VariableBinding::Nested(..) => None,
+            })
+    }
+
+    /// Checks whether the variable of the from_stack is carried over by any binding
+    pub fn binds(&self, child_name: &str) -> bool {
+        match &self.bindings {
+            Some(bindings) => bindings.bindings.iter().any(|binding| match binding {
+                VariableBinding::Single(val)
+                | VariableBinding::Dual(_, val)
+                | VariableBinding::Default(val, _) => val == child_name,
+                VariableBinding::Literal(..) | VariableBinding::Nested(..) => false,
+            }),
+            None => false,
+        }
+    }
+
     /// Checks whether all variables, based on the bindings, are allocated in the from_stack and allocates them in the to_stack.
     pub fn allocate_and_check_all(
         &self,
@@ -24,6 +51,12 @@ impl Variables {
                 let (parent_name, child_name) = match &output {
                     VariableBinding::Single(val) => (val, val),
                     VariableBinding::Dual(parent_var, child_var) => (parent_var, child_var),
+                    VariableBinding::Literal(parent_var, _)
+                    | VariableBinding::Default(parent_var, _) => {
+                        to_stack.borrow_mut().allocate(parent_var.into());
+                        continue;
+                    }
+                    VariableBinding::Nested(..) => continue,
                 };
                 from_stack.borrow().assert_allocated(child_name)?;
                 to_stack.borrow_mut().allocate(parent_name.into());
@@ -33,7 +66,7 @@ impl Variables {
     }
 
     pub fn carry_over(
-        &mut self,
+        &self,
         to_stack: &mut StackRef,
         from_stack: &mut StackRef,
     ) -> anyhow::Result<()> {
@@ -42,6 +75,23 @@ impl Variables {
                 let (parent_name, child_name) = match &output {
                     VariableBinding::Single(val) => (val, val),
                     VariableBinding::Dual(parent_var, child_var) => (parent_var, child_var),
+                    VariableBinding::Literal(parent_var, literal) => {
+                        to_stack
+                            .borrow_mut()
+                            .set(parent_var.into(), literal.into())?;
+                        continue;
+                    }
+                    VariableBinding::Default(name, default) => {
+                        // Only a value passed explicitly overrides the default, not one of the
+                        // same name in an enclosing scope
+                        let value = from_stack
+                            .borrow()
+                            .get_own(name)
+                            .unwrap_or_else(|| default.clone());
+                        to_stack.borrow_mut().set(name.into(), value)?;
+                        continue;
+                    }
+                    VariableBinding::Nested(..) => continue,
                 };
                 trace!(
                     "Carring over variable from '{}' to '{}'",
@@ -1,12 +1,18 @@
-use std::thread::JoinHandle;
+use std::{
+    any::Any,
+    collections::HashMap,
+    io::{self, Write},
+    thread::JoinHandle,
+};
 
-use clap::Parser;
-use log::error;
+use log::info;
 use thiserror::Error;
 
 pub mod cli;
 pub mod config;
+pub mod diagnostic;
 pub mod interpreter;
+pub mod list;
 pub mod logger;
 pub mod parse;
 pub mod runner;
@@ -18,6 +24,8 @@ pub enum TaskLangError {
         "Error while loading configuration (make sure the task.toml is valid!){}", print_err(.0)
     )]
     ConfigError(anyhow::Error),
+    #[error("Error while setting up the logger{}", print_err(.0))]
+    LoggerError(anyhow::Error),
     #[error("Error while parsing module '{0}' at '{1}'{}", print_err(.2))]
     ParserError(String, String, anyhow::Error),
     #[error("Error while adding module '{0}' at '{1}'{}", print_err(.2))]
@@ -28,16 +36,58 @@ pub enum TaskLangError {
     StaticAnalysisError(String, anyhow::Error),
     #[error("Error while executing task '{0}'{}", print_err(.1))]
     ExecutionError(String, anyhow::Error),
+    #[error("A command failed while executing task '{0}'{}", print_err(.1))]
+    CommandFailedError(String, anyhow::Error),
+    #[error("Task '{0}' was interrupted{}", print_err(.2))]
+    InterruptedError(String, i32, anyhow::Error),
+    #[error("{}", print_check_errors(.0))]
+    CheckFailed(Vec<TaskLangError>),
+    #[error("Error while listing the tasks{}", print_err(.0))]
+    ListError(anyhow::Error),
     #[error("Error in the runner thread: {}", print_err(.0))]
     RunnerThreadPanic(anyhow::Error),
+    #[error("Error in the interpreter thread: {}", print_err(.0))]
+    InterpreterThreadPanic(anyhow::Error),
+}
+
+impl TaskLangError {
+    /// The exit code of `inst` for this error, see the README for the documented list
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TaskLangError::CommandFailedError(..) => 1,
+            TaskLangError::ConfigError(_) | TaskLangError::LoggerError(_) => 3,
+            TaskLangError::ParserError(..) | TaskLangError::NamespaceError(..) => 4,
+            TaskLangError::ResolveError(..) => 5,
+            TaskLangError::StaticAnalysisError(..) | TaskLangError::CheckFailed(_) => 6,
+            TaskLangError::ExecutionError(..) | TaskLangError::ListError(_) => 7,
+            TaskLangError::RunnerThreadPanic(_) | TaskLangError::InterpreterThreadPanic(_) => 70,
+            // Like a shell reports a process terminated by the signal
+            TaskLangError::InterruptedError(_, signal, _) => 128 + signal,
+        }
+    }
 }
 
 fn print_err(error: &anyhow::Error) -> String {
-    let mut repr: String = "\ncaused by:\n\n".into();
-    for (counter, source) in error.chain().enumerate() {
-        repr += &format!("\t#{}: {}\n", counter, source);
+    format!("\n{}", diagnostic::render(error).trim_end())
+}
+
+fn print_check_errors(errors: &[TaskLangError]) -> String {
+    let mut output = String::new();
+    for error in errors {
+        output.push_str(&format!("{}\n\n", error));
+    }
+    output.push_str(&format!("{} task(s) failed the check", errors.len()));
+    output
+}
+
+fn panic_to_error(panic: Box<dyn Any + Send>) -> anyhow::Error {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        anyhow::anyhow!("{}", message)
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        anyhow::anyhow!("{}", message)
+    } else {
+        anyhow::anyhow!("thread panicked with unknown payload")
     }
-    repr
 }
 
 fn create_runner_thread() -> (
@@ -50,6 +100,31 @@ fn create_runner_thread() -> (
     (runner_requester, runner_server)
 }
 
+fn create_runners(
+    config: &config::Config,
+    runner_requester: &util::channel::TwoWayChannel<
+        runner::message::RunnerRequest,
+        runner::message::RunnerResponse,
+    >,
+) -> Result<(), TaskLangError> {
+    let runner_interface = runner::interface::RunnerInterface::new(runner_requester.clone());
+
+    let mut runners = config.runner.clone();
+    runners.entry("default".into()).or_default();
+
+    for (name, runner) in runners {
+        runner_interface
+            .create(name.clone(), runner.runner_type.clone(), runner.args())
+            .map_err(|err| {
+                TaskLangError::ConfigError(
+                    anyhow::Error::new(err).context(format!("creating runner '{}'", name)),
+                )
+            })?;
+    }
+
+    Ok(())
+}
+
 fn parse_root_namespace(
     config: &config::Config,
 ) -> Result<interpreter::RootNamespace, TaskLangError> {
@@ -59,30 +134,141 @@ fn parse_root_namespace(
         let location = &module.location;
         let namespace = parse::load_and_parse(location)
             .map_err(|err| TaskLangError::ParserError(name.into(), location.into(), err))?;
+        let module_name = namespace.name.clone();
         root_namespace
             .add_root(namespace)
             .map_err(|err| TaskLangError::NamespaceError(name.into(), location.into(), err))?;
+        root_namespace.set_working_directory(&module_name, module.working_directory());
     }
 
     Ok(root_namespace)
 }
 
-pub fn run() {
-    let cli = cli::Cli::parse();
-    let config = config::Config::load(cli.task_file).unwrap();
+pub fn run() -> Result<(), TaskLangError> {
+    let cli = cli::Cli::parse_valid();
+    let config = config::Config::load(cli.task_file).map_err(TaskLangError::ConfigError)?;
+
+    logger::setup_logger(&cli.log_level).map_err(TaskLangError::LoggerError)?;
+
+    let root_namespace = parse_root_namespace(&config)?;
+
+    match cli.command {
+        cli::Command::Run {
+            task,
+            args,
+            trailing_args,
+            dry_run,
+        } => {
+            let arguments = args.into_iter().chain(trailing_args).collect();
+            run_task(&config, root_namespace, task, arguments, dry_run)
+        }
+        cli::Command::Check { task, .. } => check_tasks(root_namespace, task),
+        cli::Command::List { json } => {
+            let entries = list::list(&root_namespace);
+            let output = if json {
+                list::render_json(&entries).map_err(TaskLangError::ListError)? + "\n"
+            } else {
+                list::render(&entries)
+            };
+            print_stdout(&output).map_err(TaskLangError::ListError)
+        }
+    }
+}
+
+/// Prints to stdout without panicking, a closed pipe (e.g. `inst list | head`) is not treated as an error
+pub(crate) fn print_stdout(output: &str) -> anyhow::Result<()> {
+    match io::stdout().write_all(output.as_bytes()) {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Checks the task or all executeables if none is given, collecting the first error of each of them
+fn check_tasks(
+    root_namespace: interpreter::RootNamespace,
+    task: Option<String>,
+) -> Result<(), TaskLangError> {
+    let tasks = match task {
+        Some(task) => vec![task],
+        None => root_namespace.executeable_names(),
+    };
 
-    logger::setup_logger(&cli.log_level).unwrap();
+    // Static analysis never reaches the runners, so no runner thread is needed
+    let (runner_requester, _) = util::channel::TwoWayChannel::new_pair();
+    let mut interpreter = interpreter::Interpreter::new(root_namespace, runner_requester);
 
-    let root_namespace = parse_root_namespace(&config).unwrap();
+    let errors: Vec<TaskLangError> = tasks
+        .iter()
+        .filter_map(|task| interpreter.check(task).err())
+        .collect();
+
+    if errors.is_empty() {
+        info!("Checked {} task(s) without errors", tasks.len());
+        Ok(())
+    } else {
+        Err(TaskLangError::CheckFailed(errors))
+    }
+}
+
+fn run_task(
+    config: &config::Config,
+    root_namespace: interpreter::RootNamespace,
+    task: String,
+    arguments: HashMap<String, String>,
+    dry_run: bool,
+) -> Result<(), TaskLangError> {
+    // Signals are forwarded to the running command, so the interpreter can stop after it and still
+    // execute the finally stages, while the runner thread ends once the interpreter dropped its channel
+    util::interrupt::Interrupt::global()
+        .register()
+        .map_err(|err| TaskLangError::ExecutionError(task.clone(), err.into()))?;
 
     let (runner_requester, runner_server) = create_runner_thread();
+    create_runners(config, &runner_requester)?;
 
     let interpreter_thread = interpreter::Interpreter::run_as_new_thread(
         root_namespace,
         runner_requester,
-        cli.task.clone(),
+        task,
+        arguments,
+        dry_run,
     );
 
-    interpreter_thread.join().unwrap();
-    runner_server.join().unwrap();
+    let result = interpreter_thread
+        .join()
+        .map_err(|panic| TaskLangError::InterpreterThreadPanic(panic_to_error(panic)))?;
+    runner_server
+        .join()
+        .map_err(|panic| TaskLangError::RunnerThreadPanic(panic_to_error(panic)))?;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ok_exit_codes() {
+        let err = || anyhow::anyhow!("error");
+        let name = || String::from("test.x");
+        for (error, code) in [
+            (TaskLangError::CommandFailedError(name(), err()), 1),
+            (TaskLangError::ConfigError(err()), 3),
+            (TaskLangError::LoggerError(err()), 3),
+            (TaskLangError::ParserError(name(), name(), err()), 4),
+            (TaskLangError::NamespaceError(name(), name(), err()), 4),
+            (TaskLangError::ResolveError(name(), err()), 5),
+            (TaskLangError::StaticAnalysisError(name(), err()), 6),
+            (TaskLangError::CheckFailed(vec![]), 6),
+            (TaskLangError::ExecutionError(name(), err()), 7),
+            (TaskLangError::ListError(err()), 7),
+            (TaskLangError::RunnerThreadPanic(err()), 70),
+            (TaskLangError::InterpreterThreadPanic(err()), 70),
+            (TaskLangError::InterruptedError(name(), 2, err()), 130),
+            (TaskLangError::InterruptedError(name(), 15, err()), 143),
+        ] {
+            assert_eq!(error.exit_code(), code, "{:?}", error);
+        }
+    }
 }
@@ -0,0 +1,217 @@
+use serde::Serialize;
+
+use crate::interpreter::RootNamespace;
+use crate::parse::ast::{
+    Executeable, ExecuteableType, Namespace, NamespaceOrExecuteable, NamespaceType,
+    VariableBindings,
+};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Module,
+    Collection,
+    Task,
+    Block,
+    Run,
+    Call,
+    Assignment,
+    Conditional,
+    ForEach,
+}
+
+impl EntryKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            EntryKind::Module => "module",
+            EntryKind::Collection => "collection",
+            EntryKind::Task => "task",
+            EntryKind::Block => "block",
+            EntryKind::Run => "run",
+            EntryKind::Call => "call",
+            EntryKind::Assignment => "let",
+            EntryKind::Conditional => "if",
+            EntryKind::ForEach => "for",
+        }
+    }
+}
+
+/// A module, collection or addressable executeable with its fully qualified name
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub kind: EntryKind,
+    pub options: Vec<String>,
+    pub outputs: Vec<String>,
+    pub children: Vec<Entry>,
+}
+
+impl Entry {
+    fn from_namespace(namespace: &Namespace, prefix: Option<&str>) -> Self {
+        let name = qualify(prefix, &namespace.name);
+
+        let mut children: Vec<&NamespaceOrExecuteable> = namespace.children.values().collect();
+        children.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+
+        Self {
+            children: children
+                .into_iter()
+                .map(|child| match child {
+                    NamespaceOrExecuteable::Namespace(namespace) => {
+                        Self::from_namespace(namespace, Some(&name))
+                    }
+                    NamespaceOrExecuteable::Executeable(executeable) => {
+                        Self::from_executeable(executeable, &name)
+                    }
+                })
+                .collect(),
+            kind: match namespace.namespace_type {
+                NamespaceType::Module => EntryKind::Module,
+                NamespaceType::Collection => EntryKind::Collection,
+            },
+            options: Vec::new(),
+            outputs: Vec::new(),
+            name,
+        }
+    }
+
+    fn from_executeable(executeable: &Executeable, prefix: &str) -> Self {
+        Self {
+            name: qualify(Some(prefix), &executeable.name),
+            kind: match executeable.executeable_type {
+                ExecuteableType::Command { .. } => EntryKind::Run,
+                ExecuteableType::Call { .. } => EntryKind::Call,
+                ExecuteableType::Block { .. } => EntryKind::Block,
+                ExecuteableType::Task { .. } => EntryKind::Task,
+                ExecuteableType::Assignment { .. } => EntryKind::Assignment,
+                ExecuteableType::Conditional { .. } => EntryKind::Conditional,
+                ExecuteableType::ForEach { .. } => EntryKind::ForEach,
+            },
+            options: bindings_to_strings(&executeable.options),
+            outputs: bindings_to_strings(&executeable.output_variables),
+            children: Vec::new(),
+        }
+    }
+
+    fn render(&self, depth: usize, target: &mut String) {
+        target.push_str(&"  ".repeat(depth));
+        target.push_str(&self.name);
+        target.push_str(" [");
+        target.push_str(self.kind.as_str());
+        target.push(']');
+        if !self.options.is_empty() {
+            target.push_str(&format!(" with ({})", self.options.join(", ")));
+        }
+        if !self.outputs.is_empty() {
+            target.push_str(&format!(" outputs ({})", self.outputs.join(", ")));
+        }
+        target.push('\n');
+
+        for child in &self.children {
+            child.render(depth + 1, target);
+        }
+    }
+}
+
+fn qualify(prefix: Option<&str>, name: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{}.{}", prefix, name),
+        None => name.into(),
+    }
+}
+
+fn bindings_to_strings(bindings: &Option<VariableBindings>) -> Vec<String> {
+    match bindings {
+        Some(bindings) => bindings
+            .bindings
+            .iter()
+            .map(|binding| binding.to_string())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Collects the tree of all modules, ordered by name on each level
+pub fn list(root_namespace: &RootNamespace) -> Vec<Entry> {
+    root_namespace
+        .modules()
+        .into_iter()
+        .map(|module| Entry::from_namespace(module, None))
+        .collect()
+}
+
+/// Renders the tree with one indented line per entry
+pub fn render(entries: &[Entry]) -> String {
+    let mut target = String::new();
+    for entry in entries {
+        entry.render(0, &mut target);
+    }
+    target
+}
+
+pub fn render_json(entries: &[Entry]) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(entries)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_root_namespace(modules: &[&str]) -> RootNamespace {
+        let mut root_namespace = RootNamespace::new();
+        for module in modules {
+            let namespace = crate::parse::parse("test.inst", (*module).into()).unwrap();
+            root_namespace.add_root(namespace).unwrap();
+        }
+        root_namespace
+    }
+
+    #[test]
+    fn ok_render() {
+        let root_namespace = get_root_namespace(&[
+            "module as b;\nrun as x: echo x;",
+            "module as a;\ncollection as c: {\n  let (out: stdout) from task with (stdin: input) as t: {\n    run as main: cat;\n  };\n};\nlet (status) from run with (allow_status: \"0,1\", timeout: 1s, stdin: \"a \\\"b\\\"\\n\") as r: false;",
+        ]);
+
+        assert_eq!(
+            render(&list(&root_namespace)),
+            [
+                "a [module]",
+                "  a.c [collection]",
+                "    a.c.t [task] with (stdin: input) outputs (out: stdout)",
+                r#"  a.r [run] with (allow_status: "0,1", timeout: 1s, stdin: "a \"b\"\n") outputs (status)"#,
+                "b [module]",
+                "  b.x [run]",
+                ""
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn ok_render_json() {
+        let root_namespace = get_root_namespace(&[
+            "module as a;\ntask with (env, region = \"eu\") as t: {\n  run as main: echo;\n};",
+        ]);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_json(&list(&root_namespace)).unwrap()).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!([{
+                "name": "a",
+                "kind": "module",
+                "options": [],
+                "outputs": [],
+                "children": [{
+                    "name": "a.t",
+                    "kind": "task",
+                    "options": ["env", "region = \"eu\""],
+                    "outputs": [],
+                    "children": []
+                }]
+            }])
+        );
+    }
+}
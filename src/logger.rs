@@ -1,14 +1,21 @@
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use fern::{
     self,
     colors::{Color, ColoredLevelConfig},
 };
+use lazy_static::lazy_static;
 use log::{Level, LevelFilter};
 
+lazy_static! {
+    /// The messages held back while a command owns the terminal, `None` while they are printed
+    static ref HELD_BACK: Mutex<Option<Vec<String>>> = Mutex::new(None);
+}
+
 pub fn setup_logger(log_level: &Option<String>) -> anyhow::Result<()> {
     let level = match log_level {
-        Some(val) => LevelFilter::from_str(val).unwrap(),
+        Some(val) => LevelFilter::from_str(val)?,
         None => LevelFilter::Info,
     };
 
@@ -38,8 +45,28 @@ pub fn setup_logger(log_level: &Option<String>) -> anyhow::Result<()> {
             }
         })
         .level(level)
-        .chain(std::io::stdout())
+        .chain(fern::Output::call(|record| {
+            let message = record.args().to_string();
+            match HELD_BACK.lock().unwrap().as_mut() {
+                Some(held_back) => held_back.push(message),
+                None => println!("{}", message),
+            }
+        }))
         .apply()?;
 
     Ok(())
 }
+
+/// Holds back all messages until released, so nothing is printed while a command owns the terminal
+pub fn hold_back() {
+    *HELD_BACK.lock().unwrap() = Some(Vec::new());
+}
+
+/// Prints the messages held back and all following ones right away again
+pub fn release() {
+    if let Some(held_back) = HELD_BACK.lock().unwrap().take() {
+        for message in held_back {
+            println!("{}", message);
+        }
+    }
+}
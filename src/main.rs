@@ -1,3 +1,8 @@
+use std::process;
+
 fn main() {
-    instruct::run();
+    if let Err(err) = instruct::run() {
+        eprintln!("{}", err);
+        process::exit(err.exit_code());
+    }
 }
@@ -1,10 +1,15 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
+
+pub use super::span::Span;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Namespace {
     pub name: String,
     pub namespace_type: NamespaceType,
     pub children: HashMap<String, NamespaceOrExecuteable>,
+    /// The `let` assignments of the namespace in their order of declaration
+    pub constants: Vec<Executeable>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -34,12 +39,31 @@ pub struct Executeable {
     pub name: String,
     pub options: Option<VariableBindings>,
     pub executeable_type: ExecuteableType,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum VariableBinding {
     Single(String),
     Dual(String, String),
+    Literal(String, String),
+    /// Like `Single`, but falls back to the default value if the variable is not available
+    Default(String, String),
+    /// A named list of bindings, like `env: (KEY: var, OTHER: "lit")`
+    Nested(String, VariableBindings),
+}
+
+impl VariableBinding {
+    /// The name the binding is given as, which is the first one of a `Dual`
+    pub fn name(&self) -> &str {
+        match self {
+            VariableBinding::Single(name)
+            | VariableBinding::Dual(name, _)
+            | VariableBinding::Literal(name, _)
+            | VariableBinding::Default(name, _)
+            | VariableBinding::Nested(name, _) => name,
+        }
+    }
 }
 
 impl From<&str> for VariableBinding {
@@ -54,11 +78,54 @@ impl From<(&str, &str)> for VariableBinding {
     }
 }
 
+impl fmt::Display for VariableBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableBinding::Single(name) => write!(f, "{}", name),
+            VariableBinding::Dual(name, value) => write!(f, "{}: {}", name, value),
+            VariableBinding::Literal(name, value) => write!(f, "{}: {}", name, literal(value)),
+            VariableBinding::Default(name, value) => write!(f, "{} = {}", name, quoted(value)),
+            VariableBinding::Nested(name, bindings) => write!(f, "{}: ({})", name, bindings),
+        }
+    }
+}
+
+/// The literal as it is written in a file, where a number like `5s` is not quoted
+fn literal(value: &str) -> String {
+    let units = value.trim_start_matches(|c: char| c.is_ascii_digit());
+    if units.len() < value.len() && units.chars().all(|c| c.is_ascii_alphabetic()) {
+        value.into()
+    } else {
+        quoted(value)
+    }
+}
+
+/// The value as a double quoted string, in which `\\`, `\"` and `\n` are escaped
+fn quoted(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct VariableBindings {
     pub bindings: Vec<VariableBinding>,
 }
 
+impl fmt::Display for VariableBindings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, binding) in self.bindings.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", binding)?;
+        }
+        Ok(())
+    }
+}
+
 impl VariableBindings {
     pub fn find(&self, name: &str) -> Option<&str> {
         for binding in &self.bindings {
@@ -66,7 +133,11 @@ impl VariableBindings {
                 VariableBinding::Single(binding_name) if binding_name == name => {
                     return Some(binding_name)
                 }
-                VariableBinding::Dual(binding_name, value) if binding_name == name => {
+                VariableBinding::Dual(binding_name, value)
+                | VariableBinding::Literal(binding_name, value)
+                | VariableBinding::Default(binding_name, value)
+                    if binding_name == name =>
+                {
                     return Some(value)
                 }
                 _ => (),
@@ -74,12 +145,104 @@ impl VariableBindings {
         }
         None
     }
+
+    /// The value of the binding, if it is a string literal
+    pub fn find_literal(&self, name: &str) -> Option<&str> {
+        self.bindings.iter().find_map(|binding| match binding {
+            VariableBinding::Literal(binding_name, value) if binding_name == name => Some(&**value),
+            _ => None,
+        })
+    }
+
+    pub fn find_nested(&self, name: &str) -> Option<&VariableBindings> {
+        self.bindings.iter().find_map(|binding| match binding {
+            VariableBinding::Nested(binding_name, bindings) if binding_name == name => {
+                Some(bindings)
+            }
+            _ => None,
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ExecuteableType {
-    Command { cmd: String },
-    Call { target: String },
-    Block { executeables: Vec<Executeable> },
-    Task { executeables: Vec<Executeable> },
+    Command {
+        cmd: String,
+    },
+    Call {
+        target: String,
+    },
+    Block {
+        executeables: Vec<Executeable>,
+    },
+    Task {
+        executeables: Vec<Executeable>,
+    },
+    Assignment {
+        variable: String,
+        value: String,
+    },
+    /// Executes the first list of executeables if the condition holds, the second one otherwise
+    Conditional {
+        condition: Condition,
+        then: Vec<Executeable>,
+        otherwise: Option<Vec<Executeable>>,
+    },
+    /// Executes the executeables once for every element, which is bound to the variable
+    ForEach {
+        variable: String,
+        elements: Elements,
+        executeables: Vec<Executeable>,
+    },
+}
+
+/// What a `for` loop iterates over, all values are interpolated before being split
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Elements {
+    Split(String, Split),
+    List(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Split {
+    Lines,
+    Words,
+    Separator(String),
+}
+
+impl fmt::Display for Elements {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Elements::Split(value, Split::Lines) => write!(f, "{:?} split lines", value),
+            Elements::Split(value, Split::Words) => write!(f, "{:?} split words", value),
+            Elements::Split(value, Split::Separator(separator)) => {
+                write!(f, "{:?} split {:?}", value, separator)
+            }
+            Elements::List(values) => write!(f, "{:?}", values),
+        }
+    }
+}
+
+/// The condition of an `if`, all of its operands are interpolated before being evaluated
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Condition {
+    Equals(String, String),
+    NotEquals(String, String),
+    /// Whether a file or directory exists at the path
+    Exists(String),
+    /// Whether the value is neither empty nor one of `0`, `false` and `no`
+    Truthy(String),
+    Not(Box<Condition>),
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Equals(left, right) => write!(f, "{:?} == {:?}", left, right),
+            Condition::NotEquals(left, right) => write!(f, "{:?} != {:?}", left, right),
+            Condition::Exists(path) => write!(f, "exists({:?})", path),
+            Condition::Truthy(value) => write!(f, "{:?}", value),
+            Condition::Not(condition) => write!(f, "!{}", condition),
+        }
+    }
 }
@@ -0,0 +1,112 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::char,
+    combinator::opt,
+    error::ParseError,
+    sequence::{delimited, preceded},
+    IResult,
+};
+
+use crate::parse::ast::Condition;
+use crate::parse::combinator::variable::operand;
+use crate::parse::combinator::whitespace::space_or_comment0;
+use crate::parse::span::Input;
+
+/// A condition, optionally negated by a leading `!`
+pub fn condition<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Condition, E> {
+    let (i, negated) = opt(preceded(space_or_comment0, char('!')))(i)?;
+    let (i, condition) = alt((exists_condition, comparison_condition, truthy_condition))(i)?;
+    match negated {
+        Some(_) => Ok((i, Condition::Not(Box::new(condition)))),
+        None => Ok((i, condition)),
+    }
+}
+
+fn exists_condition<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Condition, E> {
+    let (i, _) = preceded(space_or_comment0, tag("exists"))(i)?;
+    let (i, path) = delimited(
+        preceded(space_or_comment0, char('(')),
+        operand,
+        preceded(space_or_comment0, char(')')),
+    )(i)?;
+    Ok((i, Condition::Exists(path)))
+}
+
+fn comparison_condition<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Condition, E> {
+    let (i, left) = operand(i)?;
+    let (i, operator) = preceded(space_or_comment0, alt((tag("=="), tag("!="))))(i)?;
+    let (i, right) = operand(i)?;
+    match *operator.fragment() {
+        "==" => Ok((i, Condition::Equals(left, right))),
+        _ => Ok((i, Condition::NotEquals(left, right))),
+    }
+}
+
+fn truthy_condition<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Condition, E> {
+    let (i, value) = operand(i)?;
+    Ok((i, Condition::Truthy(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::combinator::testing::parse_str;
+
+    #[test]
+    fn ok_equals() {
+        assert_eq!(
+            parse_str(condition, "${status} == \"0\""),
+            Ok(("", Condition::Equals("${status}".into(), "0".into())))
+        );
+    }
+
+    #[test]
+    fn ok_not_equals() {
+        assert_eq!(
+            parse_str(condition, " \"${a}-x\"!=${env.B:-b}"),
+            Ok((
+                "",
+                Condition::NotEquals("${a}-x".into(), "${env.B:-b}".into())
+            ))
+        );
+    }
+
+    #[test]
+    fn ok_exists() {
+        assert_eq!(
+            parse_str(condition, "exists( \"${dir}/file\" )"),
+            Ok(("", Condition::Exists("${dir}/file".into())))
+        );
+    }
+
+    #[test]
+    fn ok_truthy() {
+        assert_eq!(
+            parse_str(condition, "${verbose}:"),
+            Ok((":", Condition::Truthy("${verbose}".into())))
+        );
+    }
+
+    #[test]
+    fn ok_negated() {
+        assert_eq!(
+            parse_str(condition, "!exists(\"file\")"),
+            Ok((
+                "",
+                Condition::Not(Box::new(Condition::Exists("file".into())))
+            ))
+        );
+    }
+
+    #[test]
+    fn nok_bare_word() {
+        assert!(parse_str(condition, "status == \"0\"").is_err());
+    }
+}
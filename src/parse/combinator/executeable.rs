@@ -6,56 +6,127 @@ use std::{
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_until},
-    character::complete::space0,
-    character::complete::{char, multispace0},
-    combinator::opt,
+    character::complete::char,
+    combinator::{cut, opt, value},
     error::ParseError,
-    multi::many1,
-    sequence::{delimited, preceded, terminated},
-    IResult,
+    multi::{many1, separated_list1},
+    sequence::{delimited, pair, preceded, terminated},
+    IResult, Slice,
 };
 
-use crate::parse::ast::{Executeable, ExecuteableType, NamespaceOrExecuteable};
+use crate::parse::ast::{
+    Elements, Executeable, ExecuteableType, NamespaceOrExecuteable, Span, Split,
+};
+use crate::parse::combinator::condition::condition;
 use crate::parse::combinator::variable::{
-    option_variable_bindings, output_variable_bindings, variable,
+    operand, option_variable_bindings, output_variable_bindings, string_literal, variable,
 };
+use crate::parse::combinator::whitespace::{multispace_or_comment0, space_or_comment0};
+use crate::parse::span::Input;
 
-pub fn executeable<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, Executeable, E> {
+pub fn executeable<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Executeable, E> {
     alt((
+        assignment_executeable,
         command_executeable,
         call_executeable,
         block_executeable,
         task_executeable,
+        conditional_executeable,
+        for_each_executeable,
     ))(i)
 }
 
-pub fn executeable_or<'a, E: ParseError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, NamespaceOrExecuteable, E> {
+pub fn executeable_or<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, NamespaceOrExecuteable, E> {
     let (i, executeable) = executeable(i)?;
     Ok((i, NamespaceOrExecuteable::Executeable(executeable)))
 }
 
-pub fn executor_name<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, String, E> {
-    let (i, _) = preceded(space0, tag("as"))(i)?;
-    let (i, name) = preceded(space0, variable)(i)?;
+pub fn executor_name<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, String, E> {
+    let (i, _) = preceded(space_or_comment0, tag("as"))(i)?;
+    let (i, name) = preceded(space_or_comment0, variable)(i)?;
     Ok((i, name))
 }
 
-fn command_executeable<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&str, Executeable, E> {
-    let (i, _) = multispace0(i)?;
-    let (i, output_variables) = opt(output_variable_bindings)(i)?;
-    let (i, _) = preceded(space0, tag("run"))(i)?;
+/// Takes everything up to the next `;`, returning the raw content and the span from `start` to its trimmed end
+fn until_semicolon<'a, E: ParseError<Input<'a>>>(
+    start: &Input<'a>,
+    i: Input<'a>,
+) -> IResult<Input<'a>, (&'a str, Span), E> {
+    let (i, content) = take_until(";")(i)?;
+    let (i, _) = char(';')(i)?;
+    let trimmed_end = content.fragment().trim_end().len();
+    let span = Span::between(start, &content.slice(trimmed_end..));
+    Ok((i, (content.fragment(), span)))
+}
+
+/// Takes a `"""` quoted command, which may span multiple lines and contain `;`, returning its dedented content
+/// and the span from `start` to the closing quotes
+fn raw_command<'a, E: ParseError<Input<'a>>>(
+    start: &Input<'a>,
+    i: Input<'a>,
+) -> IResult<Input<'a>, (String, Span), E> {
+    let (i, _) = preceded(multispace_or_comment0, tag("\"\"\""))(i)?;
+    let (i, content) = cut(terminated(take_until("\"\"\""), tag("\"\"\"")))(i)?;
+    let span = Span::between(start, &i);
+    let (i, _) = preceded(multispace_or_comment0, char(';'))(i)?;
+    Ok((i, (dedent(content.fragment()), span)))
+}
+
+/// Removes the line break after the opening quotes, the whitespace before the closing quotes and the
+/// indentation all non blank lines have in common
+fn dedent(content: &str) -> String {
+    let content = content
+        .strip_prefix("\r\n")
+        .or_else(|| content.strip_prefix('\n'))
+        .unwrap_or(content)
+        .trim_end();
+    let indentation = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(indentation)
+        .min()
+        .unwrap_or(0);
+    content
+        .lines()
+        .map(|line| &line[indentation.min(self::indentation(line))..])
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The length of the leading spaces and tabs, where other whitespace is part of the content
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+fn hash_name<T: Hash>(value: &T) -> String {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish().to_string()
+}
+
+fn command_executeable<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Executeable, E> {
+    let (start, _) = multispace_or_comment0(i)?;
+    let (i, output_variables) = opt(output_variable_bindings)(start)?;
+    let (i, _) = preceded(space_or_comment0, tag("run"))(i)?;
     let (i, options) = opt(option_variable_bindings)(i)?;
     let (i, opt_name) = opt(executor_name)(i)?;
-    let (i, _) = preceded(space0, char(':'))(i)?;
-    let (i, cmd) = terminated(take_until(";"), char(';'))(i)?;
-    let name = match opt_name {
-        Some(val) => val,
-        None => {
-            let mut hasher = DefaultHasher::new();
-            cmd.hash(&mut hasher);
-            hasher.finish().to_string()
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let (i, (name, cmd, span)) = match raw_command::<E>(&start, i) {
+        Err(nom::Err::Error(_)) => {
+            let (i, (cmd, span)) = until_semicolon(&start, i)?;
+            let name = opt_name.unwrap_or_else(|| hash_name(&cmd));
+            (i, (name, cmd.trim().into(), span))
+        }
+        result => {
+            let (i, (cmd, span)) = result?;
+            let name = opt_name.unwrap_or_else(|| hash_name(&cmd));
+            (i, (name, cmd, span))
         }
     };
     Ok((
@@ -64,60 +135,73 @@ fn command_executeable<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&str,
             output_variables,
             name,
             options,
-            executeable_type: ExecuteableType::Command {
-                cmd: cmd.trim().into(),
-            },
+            executeable_type: ExecuteableType::Command { cmd },
+            span,
         },
     ))
 }
 
-fn call_executeable<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&str, Executeable, E> {
-    let (i, _) = multispace0(i)?;
-    let (i, output_variables) = opt(output_variable_bindings)(i)?;
-    let (i, _) = preceded(space0, tag("call"))(i)?;
+pub fn assignment_executeable<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Executeable, E> {
+    let (start, _) = multispace_or_comment0(i)?;
+    let (i, _) = preceded(space_or_comment0, tag("let"))(start)?;
+    let (i, variable) = preceded(space_or_comment0, variable)(i)?;
+    let (i, _) = preceded(space_or_comment0, char('='))(i)?;
+    let (i, value) = preceded(space_or_comment0, string_literal)(i)?;
+    let span = Span::between(&start, &i);
+    let (i, _) = preceded(space_or_comment0, char(';'))(i)?;
+    Ok((
+        i,
+        Executeable {
+            output_variables: None,
+            name: hash_name(&(&variable, &value)),
+            options: None,
+            executeable_type: ExecuteableType::Assignment { variable, value },
+            span,
+        },
+    ))
+}
+
+fn call_executeable<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Executeable, E> {
+    let (start, _) = multispace_or_comment0(i)?;
+    let (i, output_variables) = opt(output_variable_bindings)(start)?;
+    let (i, _) = preceded(space_or_comment0, tag("call"))(i)?;
+    let (i, options) = opt(option_variable_bindings)(i)?;
     let (i, opt_name) = opt(executor_name)(i)?;
-    let (i, _) = preceded(space0, char(':'))(i)?;
-    let (i, target) = terminated(take_until(";"), char(';'))(i)?;
-    let name = match opt_name {
-        Some(val) => val,
-        None => {
-            let mut hasher = DefaultHasher::new();
-            target.hash(&mut hasher);
-            hasher.finish().to_string()
-        }
-    };
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let (i, (target, span)) = until_semicolon(&start, i)?;
+    let name = opt_name.unwrap_or_else(|| hash_name(&target));
+    let target = target.trim().into();
     Ok((
         i,
         Executeable {
             output_variables,
             name,
-            options: None,
-            executeable_type: ExecuteableType::Call {
-                target: target.trim().into(),
-            },
+            options,
+            executeable_type: ExecuteableType::Call { target },
+            span,
         },
     ))
 }
 
-fn block_executeable<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&str, Executeable, E> {
-    let (i, _) = multispace0(i)?;
-    let (i, output_variables) = opt(output_variable_bindings)(i)?;
-    let (i, _) = preceded(space0, tag("block"))(i)?;
+fn block_executeable<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Executeable, E> {
+    let (start, _) = multispace_or_comment0(i)?;
+    let (i, output_variables) = opt(output_variable_bindings)(start)?;
+    let (i, _) = preceded(space_or_comment0, tag("block"))(i)?;
     let (i, options) = opt(option_variable_bindings)(i)?;
     let (i, opt_name) = opt(executor_name)(i)?;
-    let (i, _) = preceded(space0, char(':'))(i)?;
-    let (i, _) = delimited(space0, char('{'), multispace0)(i)?;
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let span = Span::between(&start, &i);
+    let (i, _) = delimited(space_or_comment0, char('{'), multispace_or_comment0)(i)?;
     let (i, execs) = many1(executeable)(i)?;
-    let (i, _) = preceded(multispace0, char('}'))(i)?;
-    let (i, _) = preceded(space0, char(';'))(i)?;
-    let name = match opt_name {
-        Some(val) => val,
-        None => {
-            let mut hasher = DefaultHasher::new();
-            execs.hash(&mut hasher);
-            hasher.finish().to_string()
-        }
-    };
+    let (i, _) = preceded(multispace_or_comment0, char('}'))(i)?;
+    let (i, _) = preceded(space_or_comment0, char(';'))(i)?;
+    let name = opt_name.unwrap_or_else(|| hash_name(&execs));
     Ok((
         i,
         Executeable {
@@ -127,23 +211,25 @@ fn block_executeable<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&str, Ex
             executeable_type: ExecuteableType::Block {
                 executeables: execs,
             },
+            span,
         },
     ))
 }
 
-pub fn task_executeable<'a, E: ParseError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, Executeable, E> {
-    let (i, _) = multispace0(i)?;
-    let (i, output_variables) = opt(output_variable_bindings)(i)?;
-    let (i, _) = preceded(space0, tag("task"))(i)?;
+pub fn task_executeable<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Executeable, E> {
+    let (start, _) = multispace_or_comment0(i)?;
+    let (i, output_variables) = opt(output_variable_bindings)(start)?;
+    let (i, _) = preceded(space_or_comment0, tag("task"))(i)?;
     let (i, options) = opt(option_variable_bindings)(i)?;
     let (i, name) = executor_name(i)?;
-    let (i, _) = preceded(space0, char(':'))(i)?;
-    let (i, _) = delimited(space0, char('{'), multispace0)(i)?;
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let span = Span::between(&start, &i);
+    let (i, _) = delimited(space_or_comment0, char('{'), multispace_or_comment0)(i)?;
     let (i, executeables) = many1(executeable)(i)?;
-    let (i, _) = preceded(multispace0, char('}'))(i)?;
-    let (i, _) = preceded(space0, char(';'))(i)?;
+    let (i, _) = preceded(multispace_or_comment0, char('}'))(i)?;
+    let (i, _) = preceded(space_or_comment0, char(';'))(i)?;
     Ok((
         i,
         Executeable {
@@ -151,6 +237,114 @@ pub fn task_executeable<'a, E: ParseError<&'a str>>(
             name,
             options,
             executeable_type: ExecuteableType::Task { executeables },
+            span,
+        },
+    ))
+}
+
+/// The executeables within `{` and `}`
+fn executeable_list<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Vec<Executeable>, E> {
+    let (i, _) = delimited(space_or_comment0, char('{'), multispace_or_comment0)(i)?;
+    let (i, executeables) = many1(executeable)(i)?;
+    let (i, _) = preceded(multispace_or_comment0, char('}'))(i)?;
+    Ok((i, executeables))
+}
+
+fn conditional_executeable<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Executeable, E> {
+    let (start, _) = multispace_or_comment0(i)?;
+    let (i, _) = preceded(space_or_comment0, tag("if"))(start)?;
+    let (i, condition) = condition(i)?;
+    let (i, opt_name) = opt(executor_name)(i)?;
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let span = Span::between(&start, &i);
+    let (i, then) = executeable_list(i)?;
+    let (i, otherwise) = opt(preceded(
+        pair(
+            preceded(multispace_or_comment0, tag("else")),
+            preceded(space_or_comment0, char(':')),
+        ),
+        executeable_list,
+    ))(i)?;
+    let (i, _) = preceded(space_or_comment0, char(';'))(i)?;
+    let name = opt_name.unwrap_or_else(|| hash_name(&(&condition, &then, &otherwise)));
+    Ok((
+        i,
+        Executeable {
+            output_variables: None,
+            name,
+            options: None,
+            executeable_type: ExecuteableType::Conditional {
+                condition,
+                then,
+                otherwise,
+            },
+            span,
+        },
+    ))
+}
+
+/// A list of values in parentheses or a value, which is split into lines unless specified otherwise
+fn elements<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Elements, E> {
+    alt((list_elements, split_elements))(i)
+}
+
+fn list_elements<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Elements, E> {
+    let (i, values) = delimited(
+        preceded(space_or_comment0, char('(')),
+        separated_list1(preceded(space_or_comment0, char(',')), operand),
+        preceded(space_or_comment0, char(')')),
+    )(i)?;
+    Ok((i, Elements::List(values)))
+}
+
+fn split_elements<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Elements, E> {
+    let (i, value) = operand(i)?;
+    let (i, split) = opt(preceded(preceded(space_or_comment0, tag("split")), split))(i)?;
+    Ok((i, Elements::Split(value, split.unwrap_or(Split::Lines))))
+}
+
+fn split<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Split, E> {
+    let (i, _) = space_or_comment0(i)?;
+    match opt(string_literal)(i)? {
+        (i, Some(separator)) => Ok((i, Split::Separator(separator))),
+        (i, None) => alt((
+            value(Split::Lines, tag("lines")),
+            value(Split::Words, tag("words")),
+        ))(i),
+    }
+}
+
+fn for_each_executeable<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Executeable, E> {
+    let (start, _) = multispace_or_comment0(i)?;
+    let (i, output_variables) = opt(output_variable_bindings)(start)?;
+    let (i, _) = preceded(space_or_comment0, tag("for"))(i)?;
+    let (i, variable) = preceded(space_or_comment0, variable)(i)?;
+    let (i, _) = preceded(space_or_comment0, tag("in"))(i)?;
+    let (i, elements) = elements(i)?;
+    let (i, opt_name) = opt(executor_name)(i)?;
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let span = Span::between(&start, &i);
+    let (i, executeables) = executeable_list(i)?;
+    let (i, _) = preceded(space_or_comment0, char(';'))(i)?;
+    let name = opt_name.unwrap_or_else(|| hash_name(&(&variable, &elements, &executeables)));
+    Ok((
+        i,
+        Executeable {
+            output_variables,
+            name,
+            options: None,
+            executeable_type: ExecuteableType::ForEach {
+                variable,
+                elements,
+                executeables,
+            },
+            span,
         },
     ))
 }
@@ -159,7 +353,7 @@ pub fn task_executeable<'a, E: ParseError<&'a str>>(
 mod tests {
     use super::*;
     use crate::parse::ast::VariableBindings;
-    use nom::error::Error;
+    use crate::parse::combinator::testing::parse_str;
 
     #[cfg(test)]
     mod command {
@@ -168,10 +362,11 @@ mod tests {
         #[test]
         fn ok_simple() {
             assert_eq!(
-                executeable::<Error<&str>>("run: test;"),
+                parse_str(executeable, "run: test;"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "12855381050612350598".into(),
                         options: None,
@@ -184,10 +379,11 @@ mod tests {
         #[test]
         fn ok_binding() {
             assert_eq!(
-                executeable::<Error<&str>>("let (var: stdout) from run: test;"),
+                parse_str(executeable, "let (var: stdout) from run: test;"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: Some(VariableBindings {
                             bindings: vec![("var", "stdout").into()]
                         }),
@@ -202,10 +398,11 @@ mod tests {
         #[test]
         fn ok_name() {
             assert_eq!(
-                executeable::<Error<&str>>("run as test_cmd: test;"),
+                parse_str(executeable, "run as test_cmd: test;"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "test_cmd".into(),
                         options: None,
@@ -218,10 +415,11 @@ mod tests {
         #[test]
         fn ok_options() {
             assert_eq!(
-                executeable::<Error<&str>>("run with (silent, cd: test_dir): test;"),
+                parse_str(executeable, "run with (silent, cd: test_dir): test;"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "12855381050612350598".into(),
                         options: Some(VariableBindings {
@@ -236,12 +434,14 @@ mod tests {
         #[test]
         fn ok_all() {
             assert_eq!(
-                executeable::<Error<&str>>(
+                parse_str(
+                    executeable,
                     "let (var: stdout) from run with (silent, cd: test_dir) as test_cmd: test;"
                 ),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: Some(VariableBindings {
                             bindings: vec![("var", "stdout").into()]
                         }),
@@ -256,6 +456,121 @@ mod tests {
         }
     }
 
+    #[cfg(test)]
+    mod raw_command {
+        use super::*;
+
+        fn get_cmd(input: &str) -> String {
+            let (i, executeable) = parse_str(executeable, input).unwrap();
+            assert_eq!(i, "");
+            match executeable.executeable_type {
+                ExecuteableType::Command { cmd } => cmd,
+                other => panic!("expected command, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn ok_single_line() {
+            assert_eq!(
+                get_cmd(r#"run: """find . -exec rm {} \;""";"#),
+                r"find . -exec rm {} \;"
+            );
+        }
+
+        #[test]
+        fn ok_multi_line() {
+            assert_eq!(
+                get_cmd("run as loop: \"\"\"\n    for i in 1 2; do\n      echo ${i};\n    done\n    \"\"\";"),
+                "for i in 1 2; do\n  echo ${i};\ndone"
+            );
+        }
+
+        #[test]
+        fn ok_braces() {
+            assert_eq!(
+                get_cmd(r#"run with (shell): """echo '{"a": 1}' > out.json""" ;"#),
+                r#"echo '{"a": 1}' > out.json"#
+            );
+        }
+
+        #[test]
+        fn ok_blank_lines() {
+            assert_eq!(get_cmd("run: \"\"\"\n  a\n\n    b\n\"\"\";"), "a\n\n  b");
+        }
+
+        #[test]
+        fn ok_non_ascii_whitespace_kept() {
+            assert_eq!(
+                get_cmd("run: \"\"\"\n  a\n \u{a0}b\n\"\"\";"),
+                " a\n\u{a0}b"
+            );
+        }
+
+        #[test]
+        fn ok_inside_block() {
+            let (i, block) = parse_str(
+                executeable,
+                "block: {\n  run: \"\"\"\n    a; b\n  \"\"\";\n  run: c;\n};",
+            )
+            .unwrap();
+            assert_eq!(i, "");
+            match block.executeable_type {
+                ExecuteableType::Block { executeables } => assert_eq!(executeables.len(), 2),
+                other => panic!("expected block, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn nok_unclosed() {
+            assert!(parse_str(executeable, "run: \"\"\"echo a; echo b;").is_err());
+        }
+
+        #[test]
+        fn nok_missing_semicolon() {
+            assert!(parse_str(executeable, "run: \"\"\"echo a\"\"\"").is_err());
+        }
+    }
+
+    #[cfg(test)]
+    mod assignment {
+        use super::*;
+
+        fn get_assignment(input: &str) -> (String, String) {
+            let (i, executeable) = parse_str(executeable, input).unwrap();
+            assert_eq!(i, "");
+            match executeable.executeable_type {
+                ExecuteableType::Assignment { variable, value } => (variable, value),
+                other => panic!("expected assignment, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn ok_simple() {
+            assert_eq!(
+                get_assignment("let name = \"value\";"),
+                ("name".into(), "value".into())
+            );
+        }
+
+        #[test]
+        fn ok_interpolated() {
+            assert_eq!(
+                get_assignment("  let name=\"a ${b}; c\" ;"),
+                ("name".into(), "a ${b}; c".into())
+            );
+        }
+
+        #[test]
+        fn nok_unquoted() {
+            assert!(parse_str(executeable, "let name = value;").is_err());
+        }
+
+        #[test]
+        fn nok_missing_semicolon() {
+            assert!(parse_str(executeable, "let name = \"value\"").is_err());
+        }
+    }
+
     #[cfg(test)]
     mod call {
         use super::*;
@@ -263,10 +578,11 @@ mod tests {
         #[test]
         fn ok_simple() {
             assert_eq!(
-                executeable::<Error<&str>>("call: test;"),
+                parse_str(executeable, "call: test;"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "12855381050612350598".into(),
                         options: None,
@@ -281,10 +597,11 @@ mod tests {
         #[test]
         fn ok_binding() {
             assert_eq!(
-                executeable::<Error<&str>>("let (var: stdout) from call: test;"),
+                parse_str(executeable, "let (var: stdout) from call: test;"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: Some(VariableBindings {
                             bindings: vec![("var", "stdout").into()]
                         }),
@@ -301,10 +618,11 @@ mod tests {
         #[test]
         fn ok_name() {
             assert_eq!(
-                executeable::<Error<&str>>("call as test_call: test;"),
+                parse_str(executeable, "call as test_call: test;"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "test_call".into(),
                         options: None,
@@ -316,18 +634,48 @@ mod tests {
             );
         }
 
+        #[test]
+        fn ok_options() {
+            assert_eq!(
+                parse_str(
+                    executeable,
+                    "call with (env: target_env, region) as main: test;"
+                ),
+                Ok((
+                    "",
+                    Executeable {
+                        span: Span::default(),
+                        output_variables: None,
+                        name: "main".into(),
+                        options: Some(VariableBindings {
+                            bindings: vec![("env", "target_env").into(), "region".into()]
+                        }),
+                        executeable_type: ExecuteableType::Call {
+                            target: "test".into()
+                        }
+                    }
+                ))
+            );
+        }
+
         #[test]
         fn ok_all() {
             assert_eq!(
-                executeable::<Error<&str>>("let (var: stdout) from call as test_call: test;"),
+                parse_str(
+                    executeable,
+                    "let (var: stdout) from call with (env) as test_call: test;"
+                ),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: Some(VariableBindings {
                             bindings: vec![("var", "stdout").into()]
                         }),
                         name: "test_call".into(),
-                        options: None,
+                        options: Some(VariableBindings {
+                            bindings: vec!["env".into()]
+                        }),
                         executeable_type: ExecuteableType::Call {
                             target: "test".into()
                         }
@@ -337,6 +685,143 @@ mod tests {
         }
     }
 
+    #[cfg(test)]
+    mod conditional {
+        use super::*;
+        use crate::parse::ast::Condition;
+
+        fn get_conditional(input: &str) -> (String, Condition, Vec<String>, Option<Vec<String>>) {
+            let (i, executeable) = parse_str(executeable, input).unwrap();
+            assert_eq!(i, "");
+            let names = |executeables: Vec<Executeable>| {
+                executeables
+                    .into_iter()
+                    .map(|executeable| executeable.name)
+                    .collect()
+            };
+            match executeable.executeable_type {
+                ExecuteableType::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                } => (
+                    executeable.name,
+                    condition,
+                    names(then),
+                    otherwise.map(names),
+                ),
+                other => panic!("expected conditional, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn ok_if_else() {
+            let (_, condition, then, otherwise) = get_conditional(
+                "if ${status} == \"0\": {\n  run as a: test;\n} else: {\n  run as b: test;\n  run as c: test;\n};",
+            );
+            assert_eq!(condition, Condition::Equals("${status}".into(), "0".into()));
+            assert_eq!(then, vec!["a"]);
+            assert_eq!(otherwise, Some(vec!["b".into(), "c".into()]));
+        }
+
+        #[test]
+        fn ok_named_without_else() {
+            let (name, condition, then, otherwise) =
+                get_conditional("if !exists(\"file\") as main: { run as a: touch file; };");
+            assert_eq!(name, "main");
+            assert_eq!(
+                condition,
+                Condition::Not(Box::new(Condition::Exists("file".into())))
+            );
+            assert_eq!(then, vec!["a"]);
+            assert_eq!(otherwise, None);
+        }
+
+        #[test]
+        fn nok_missing_braces() {
+            assert!(parse_str(executeable, "if ${x}: run: test;;").is_err());
+        }
+
+        #[test]
+        fn nok_missing_semicolon() {
+            assert!(parse_str(executeable, "if ${x}: { run: test; }").is_err());
+        }
+    }
+
+    mod for_each {
+        use super::*;
+        use crate::parse::ast::{Elements, Split, VariableBinding};
+
+        fn get_for_each(input: &str) -> (String, Elements, Vec<String>) {
+            let (i, executeable) = parse_str(executeable, input).unwrap();
+            assert_eq!(i, "");
+            match executeable.executeable_type {
+                ExecuteableType::ForEach {
+                    variable,
+                    elements,
+                    executeables,
+                } => (
+                    variable,
+                    elements,
+                    executeables
+                        .into_iter()
+                        .map(|executeable| executeable.name)
+                        .collect(),
+                ),
+                other => panic!("expected for each, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn ok_lines_by_default() {
+            let (variable, elements, executeables) =
+                get_for_each("for item in ${stdout}: {\n  run as a: echo ${item};\n};");
+            assert_eq!(variable, "item");
+            assert_eq!(elements, Elements::Split("${stdout}".into(), Split::Lines));
+            assert_eq!(executeables, vec!["a"]);
+        }
+
+        #[test]
+        fn ok_split() {
+            let (_, elements, _) =
+                get_for_each("for item in ${stdout} split words: { run: echo ${item}; };");
+            assert_eq!(elements, Elements::Split("${stdout}".into(), Split::Words));
+
+            let (_, elements, _) =
+                get_for_each("for item in \"a,b\" split \",\": { run: echo ${item}; };");
+            assert_eq!(
+                elements,
+                Elements::Split("a,b".into(), Split::Separator(",".into()))
+            );
+        }
+
+        #[test]
+        fn ok_list() {
+            let (_, elements, _) =
+                get_for_each("for item in (\"a\", ${b}) as loop: { run: echo ${item}; };");
+            assert_eq!(elements, Elements::List(vec!["a".into(), "${b}".into()]));
+        }
+
+        #[test]
+        fn ok_outputs() {
+            let (i, executeable) = parse_str(
+                executeable,
+                "let (out: stdout) from for item in ${x}: { let (stdout) from run: echo ${item}; };",
+            )
+            .unwrap();
+            assert_eq!(i, "");
+            assert_eq!(
+                executeable.output_variables.unwrap().bindings,
+                vec![VariableBinding::Dual("out".into(), "stdout".into())]
+            );
+        }
+
+        #[test]
+        fn nok_missing_in() {
+            assert!(parse_str(executeable, "for item ${x}: { run: test; };").is_err());
+        }
+    }
+
     #[cfg(test)]
     mod block {
         use super::*;
@@ -344,15 +829,17 @@ mod tests {
         #[test]
         fn ok_simple() {
             assert_eq!(
-                executeable::<Error<&str>>("block: {run: test;};"),
+                parse_str(executeable, "block: {run: test;};"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "4604916792254010148".into(),
                         options: None,
                         executeable_type: ExecuteableType::Block {
                             executeables: vec![Executeable {
+                                span: Span::default(),
                                 output_variables: None,
                                 name: "12855381050612350598".into(),
                                 options: None,
@@ -367,15 +854,17 @@ mod tests {
         #[test]
         fn ok_simple_newlines() {
             assert_eq!(
-                executeable::<Error<&str>>("block: {\n\trun: test;\n} ;"),
+                parse_str(executeable, "block: {\n\trun: test;\n} ;"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "4604916792254010148".into(),
                         options: None,
                         executeable_type: ExecuteableType::Block {
                             executeables: vec![Executeable {
+                                span: Span::default(),
                                 output_variables: None,
                                 name: "12855381050612350598".into(),
                                 options: None,
@@ -390,15 +879,17 @@ mod tests {
         #[test]
         fn ok_named() {
             assert_eq!(
-                executeable::<Error<&str>>("block as pre: {run: test;};"),
+                parse_str(executeable, "block as pre: {run: test;};"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "pre".into(),
                         options: None,
                         executeable_type: ExecuteableType::Block {
                             executeables: vec![Executeable {
+                                span: Span::default(),
                                 output_variables: None,
                                 name: "12855381050612350598".into(),
                                 options: None,
@@ -413,10 +904,11 @@ mod tests {
         #[test]
         fn ok_options() {
             assert_eq!(
-                executeable::<Error<&str>>("block with (runner: sh): {run: test;};"),
+                parse_str(executeable, "block with (runner: sh): {run: test;};"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "4604916792254010148".into(),
                         options: Some(VariableBindings {
@@ -424,6 +916,7 @@ mod tests {
                         }),
                         executeable_type: ExecuteableType::Block {
                             executeables: vec![Executeable {
+                                span: Span::default(),
                                 output_variables: None,
                                 name: "12855381050612350598".into(),
                                 options: None,
@@ -438,10 +931,11 @@ mod tests {
         #[test]
         fn ok_output() {
             assert_eq!(
-                executeable::<Error<&str>>("let (var: stdout) from block: {run: test;};"),
+                parse_str(executeable, "let (var: stdout) from block: {run: test;};"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: Some(VariableBindings {
                             bindings: vec![("var", "stdout").into()]
                         }),
@@ -449,6 +943,7 @@ mod tests {
                         options: None,
                         executeable_type: ExecuteableType::Block {
                             executeables: vec![Executeable {
+                                span: Span::default(),
                                 output_variables: None,
                                 name: "12855381050612350598".into(),
                                 options: None,
@@ -463,20 +958,26 @@ mod tests {
         #[test]
         fn ok_nested() {
             assert_eq!(
-                executeable::<Error<&str>>("block as pre1: {block as pre2: {run: test;};};"),
+                parse_str(
+                    executeable,
+                    "block as pre1: {block as pre2: {run: test;};};"
+                ),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "pre1".into(),
                         options: None,
                         executeable_type: ExecuteableType::Block {
                             executeables: vec![Executeable {
+                                span: Span::default(),
                                 output_variables: None,
                                 name: "pre2".into(),
                                 options: None,
                                 executeable_type: ExecuteableType::Block {
                                     executeables: vec![Executeable {
+                                        span: Span::default(),
                                         output_variables: None,
                                         name: "12855381050612350598".into(),
                                         options: None,
@@ -495,10 +996,11 @@ mod tests {
         #[test]
         fn nok_empty() {
             assert_ne!(
-                executeable::<Error<&str>>("block: {};"),
+                parse_str(executeable, "block: {};"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "".into(),
                         options: None,
@@ -513,15 +1015,17 @@ mod tests {
         #[test]
         fn nok_missing_semicolon() {
             assert_ne!(
-                executeable::<Error<&str>>("block: {run: test;}"),
+                parse_str(executeable, "block: {run: test;}"),
                 Ok((
                     "",
                     Executeable {
+                        span: Span::default(),
                         output_variables: None,
                         name: "".into(),
                         options: None,
                         executeable_type: ExecuteableType::Block {
                             executeables: vec![Executeable {
+                                span: Span::default(),
                                 output_variables: None,
                                 name: "".into(),
                                 options: None,
@@ -533,4 +1037,72 @@ mod tests {
             );
         }
     }
+
+    #[cfg(test)]
+    mod comments {
+        use super::*;
+
+        fn get_block_commands(input: &str) -> Vec<String> {
+            let (i, block) = parse_str(executeable, input).unwrap();
+            assert_eq!(i, "");
+            match block.executeable_type {
+                ExecuteableType::Block { executeables } => executeables
+                    .into_iter()
+                    .map(|executeable| match executeable.executeable_type {
+                        ExecuteableType::Command { cmd } => cmd,
+                        other => panic!("expected command, got {:?}", other),
+                    })
+                    .collect(),
+                other => panic!("expected block, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn ok_inside_block() {
+            assert_eq!(
+                get_block_commands(
+                    "block: { # first\n  run: a;\n  // run: b;\n  run: c; /* c */\n  /* last */ };"
+                ),
+                vec!["a", "c"]
+            );
+        }
+
+        #[test]
+        fn ok_after_bindings() {
+            let (i, command) = parse_str(
+                executeable,
+                "let (var: stdout) /* out */ from run /* run */ with (trim_stdout) /* opt */ as x /* name */: test;",
+            )
+            .unwrap();
+            assert_eq!(i, "");
+            assert_eq!(command.name, "x");
+            assert_eq!(
+                command.executeable_type,
+                ExecuteableType::Command { cmd: "test".into() }
+            );
+        }
+
+        #[test]
+        fn ok_inside_bindings() {
+            let (_, command) = parse_str(
+                executeable,
+                "run with ( /* a */ trim_stdout, /* b */ stdin: var /* c */ ): test;",
+            )
+            .unwrap();
+            assert_eq!(
+                command.options,
+                Some(VariableBindings {
+                    bindings: vec!["trim_stdout".into(), ("stdin", "var").into()]
+                })
+            );
+        }
+
+        #[test]
+        fn ok_semicolon_in_comment() {
+            assert_eq!(
+                get_block_commands("block: {\n  // a; b;\n  run: c;\n  /* d; */\n};"),
+                vec!["c"]
+            );
+        }
+    }
 }
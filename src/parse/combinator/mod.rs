@@ -1,3 +1,22 @@
+pub mod condition;
 pub mod executeable;
 pub mod namespace;
 pub mod variable;
+pub mod whitespace;
+
+#[cfg(test)]
+pub mod testing {
+    use nom::{error::Error, IResult};
+
+    use crate::parse::span::Input;
+
+    /// Runs the parser on a plain string without source, mapping its remaining input back to a string
+    pub fn parse_str<'a, O>(
+        mut parser: impl FnMut(Input<'a>) -> IResult<Input<'a>, O, Error<Input<'a>>>,
+        i: &'a str,
+    ) -> IResult<&'a str, O> {
+        parser(Input::new_extra(i, None))
+            .map(|(i, output)| (*i.fragment(), output))
+            .map_err(|err| err.map(|err| Error::new(*err.input.fragment(), err.code)))
+    }
+}
@@ -3,8 +3,7 @@ use std::collections::HashMap;
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::space0,
-    character::complete::{char, multispace0},
+    character::complete::char,
     combinator::eof,
     error::ParseError,
     multi::many0,
@@ -12,42 +11,66 @@ use nom::{
     IResult,
 };
 
-use crate::parse::ast::{Namespace, NamespaceOrExecuteable, NamespaceType};
+use crate::parse::ast::{
+    Executeable, ExecuteableType, Namespace, NamespaceOrExecuteable, NamespaceType, Span,
+};
 use crate::parse::combinator::executeable::executor_name;
+use crate::parse::combinator::whitespace::{multispace_or_comment0, space_or_comment0};
+use crate::parse::span::Input;
 
 use super::executeable::executeable_or;
 
-pub fn namespace<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, Namespace, E> {
+pub fn namespace<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Namespace, E> {
     alt((collection, module))(i)
 }
 
-pub fn namespace_or<'a, E: ParseError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, NamespaceOrExecuteable, E> {
+pub fn namespace_or<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, NamespaceOrExecuteable, E> {
     let (i, namespace) = namespace(i)?;
     Ok((i, NamespaceOrExecuteable::Namespace(namespace)))
 }
 
-pub fn namespace_or_executeable_map<'a, E: ParseError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, HashMap<String, NamespaceOrExecuteable>, E> {
-    let (i, mut namespaces) = many0(alt((namespace_or, executeable_or)))(i)?;
+/// The children of a namespace by their name and its constants, which are all `let` assignments
+pub fn namespace_children<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, (HashMap<String, NamespaceOrExecuteable>, Vec<Executeable>), E> {
+    let (i, namespaces) = many0(alt((namespace_or, executeable_or)))(i)?;
+    let (constants, children): (Vec<_>, Vec<_>) = namespaces.into_iter().partition(|val| {
+        matches!(
+            val,
+            NamespaceOrExecuteable::Executeable(Executeable {
+                executeable_type: ExecuteableType::Assignment { .. },
+                ..
+            })
+        )
+    });
     Ok((
         i,
-        namespaces
-            .drain(..)
-            .map(|val| (val.get_name().into(), val))
-            .collect(),
+        (
+            children
+                .into_iter()
+                .map(|val| (val.get_name().into(), val))
+                .collect(),
+            constants
+                .into_iter()
+                .filter_map(|val| match val {
+                    NamespaceOrExecuteable::Executeable(executeable) => Some(executeable),
+                    NamespaceOrExecuteable::Namespace(_) => None,
+                })
+                .collect(),
+        ),
     ))
 }
 
-pub fn module<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, Namespace, E> {
-    let (i, _) = multispace0(i)?;
-    let (i, _) = preceded(space0, tag("module"))(i)?;
+pub fn module<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Namespace, E> {
+    let (start, _) = multispace_or_comment0(i)?;
+    let (i, _) = preceded(space_or_comment0, tag("module"))(start)?;
     let (i, name) = executor_name(i)?;
-    let (i, _) = preceded(space0, char(';'))(i)?;
-    let (i, children) = namespace_or_executeable_map(i)?;
-    let (i, _) = multispace0(i)?;
+    let (i, _) = preceded(space_or_comment0, char(';'))(i)?;
+    let span = Span::between(&start, &i);
+    let (i, (children, constants)) = namespace_children(i)?;
+    let (i, _) = multispace_or_comment0(i)?;
     let (i, _) = eof(i)?;
 
     Ok((
@@ -56,25 +79,164 @@ pub fn module<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, Namesp
             name,
             namespace_type: NamespaceType::Module,
             children,
+            constants,
+            span,
         },
     ))
 }
 
-pub fn collection<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, Namespace, E> {
-    let (i, _) = multispace0(i)?;
-    let (i, _) = preceded(space0, tag("collection"))(i)?;
+pub fn collection<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Namespace, E> {
+    let (start, _) = multispace_or_comment0(i)?;
+    let (i, _) = preceded(space_or_comment0, tag("collection"))(start)?;
     let (i, name) = executor_name(i)?;
-    let (i, _) = preceded(space0, char(':'))(i)?;
-    let (i, _) = delimited(space0, char('{'), multispace0)(i)?;
-    let (i, children) = namespace_or_executeable_map(i)?;
-    let (i, _) = preceded(multispace0, char('}'))(i)?;
-    let (i, _) = preceded(space0, char(';'))(i)?;
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let span = Span::between(&start, &i);
+    let (i, _) = delimited(space_or_comment0, char('{'), multispace_or_comment0)(i)?;
+    let (i, (children, constants)) = namespace_children(i)?;
+    let (i, _) = preceded(multispace_or_comment0, char('}'))(i)?;
+    let (i, _) = preceded(space_or_comment0, char(';'))(i)?;
     Ok((
         i,
         Namespace {
             name,
             namespace_type: NamespaceType::Collection,
             children,
+            constants,
+            span,
         },
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::combinator::testing::parse_str;
+
+    fn get_executeable(namespace: &Namespace, name: &str) -> Executeable {
+        match namespace.children.get(name) {
+            Some(NamespaceOrExecuteable::Executeable(executeable)) => executeable.clone(),
+            other => panic!("expected executeable '{}', got {:?}", name, other),
+        }
+    }
+
+    #[cfg(test)]
+    mod comments {
+        use super::*;
+
+        #[test]
+        fn ok_before_module() {
+            let (i, module) = parse_str(
+                module,
+                "# task file\n/* header */\nmodule as test;\nrun as x: test;\n",
+            )
+            .unwrap();
+            assert_eq!(i, "");
+            assert_eq!(module.name, "test");
+            assert_eq!(module.children.len(), 1);
+        }
+
+        #[test]
+        fn ok_after_module_header() {
+            let (_, module) =
+                parse_str(module, "module as test; // the module\nrun as x: test;").unwrap();
+            assert_eq!(module.children.len(), 1);
+        }
+
+        #[test]
+        fn ok_between_executeables() {
+            let (_, module) = parse_str(
+                module,
+                "module as test;\nrun as x: test;\n// run as y: disabled;\n/* run as z:\n  disabled; */\nrun as w: test;\n",
+            )
+            .unwrap();
+            assert_eq!(module.children.len(), 2);
+            assert!(module.children.contains_key("x"));
+            assert!(module.children.contains_key("w"));
+        }
+
+        #[test]
+        fn ok_after_command() {
+            let (_, module) =
+                parse_str(module, "module as test;\nrun as x: test; # trailing\n").unwrap();
+            assert_eq!(
+                get_executeable(&module, "x").executeable_type,
+                ExecuteableType::Command { cmd: "test".into() }
+            );
+        }
+
+        #[test]
+        fn ok_inside_collection() {
+            let (_, module) = parse_str(
+                module,
+                "module as test;\ncollection as c: { // collection\n  # first\n  run as x: test;\n  // last\n};\n# eof",
+            )
+            .unwrap();
+            match module.children.get("c") {
+                Some(NamespaceOrExecuteable::Namespace(collection)) => {
+                    assert_eq!(collection.children.len(), 1)
+                }
+                other => panic!("expected collection, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn ok_inside_command_is_kept() {
+            let (_, module) = parse_str(
+                module,
+                "module as test;\nrun as x: echo '#1' // not a comment;\n",
+            )
+            .unwrap();
+            assert_eq!(
+                get_executeable(&module, "x").executeable_type,
+                ExecuteableType::Command {
+                    cmd: "echo '#1' // not a comment".into()
+                }
+            );
+        }
+
+        #[test]
+        fn nok_unclosed_block_comment() {
+            assert!(parse_str(module, "module as test;\n/* run as x: test;\n").is_err());
+        }
+    }
+
+    #[cfg(test)]
+    mod constants {
+        use super::*;
+
+        fn get_constants(namespace: &Namespace) -> Vec<(String, String)> {
+            namespace
+                .constants
+                .iter()
+                .map(|constant| match &constant.executeable_type {
+                    ExecuteableType::Assignment { variable, value } => {
+                        (variable.clone(), value.clone())
+                    }
+                    other => panic!("expected assignment, got {:?}", other),
+                })
+                .collect()
+        }
+
+        #[test]
+        fn ok_module_and_collection() {
+            let (_, module) = parse_str(
+                module,
+                "module as test;\nlet b = \"1\";\nlet a = \"${b}\";\nrun as x: test;\ncollection as c: {\n  let c = \"2\";\n  run as y: test;\n};",
+            )
+            .unwrap();
+
+            assert_eq!(
+                get_constants(&module),
+                vec![("b".into(), "1".into()), ("a".into(), "${b}".into())]
+            );
+            assert_eq!(module.children.len(), 2);
+            match module.children.get("c") {
+                Some(NamespaceOrExecuteable::Namespace(collection)) => {
+                    assert_eq!(get_constants(collection), vec![("c".into(), "2".into())]);
+                    assert_eq!(collection.children.len(), 1);
+                }
+                other => panic!("expected collection, got {:?}", other),
+            }
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::char,
-    character::complete::{alpha1, alphanumeric1, space0},
-    combinator::{not, recognize},
+    bytes::complete::{escaped_transform, is_not, tag},
+    character::complete::{alpha0, alpha1, alphanumeric1, digit1},
+    character::complete::{char, none_of, one_of},
+    combinator::{map, not, opt, recognize, value},
     error::ParseError,
     multi::{many0_count, separated_list1},
     sequence::{pair, preceded},
@@ -11,66 +11,137 @@ use nom::{
 };
 
 use crate::parse::ast::{VariableBinding, VariableBindings};
+use crate::parse::combinator::whitespace::space_or_comment0;
+use crate::parse::span::Input;
 
-pub fn output_variable_bindings<'a, E: ParseError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, VariableBindings, E> {
-    let (i, _) = preceded(space0, tag("let"))(i)?;
+pub fn output_variable_bindings<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, VariableBindings, E> {
+    let (i, _) = preceded(space_or_comment0, tag("let"))(i)?;
     let (i, bindings) = variable_bindings(i)?;
-    let (i, _) = preceded(space0, tag("from"))(i)?;
+    let (i, _) = preceded(space_or_comment0, tag("from"))(i)?;
     Ok((i, bindings))
 }
 
-pub fn option_variable_bindings<'a, E: ParseError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, VariableBindings, E> {
-    let (i, _) = preceded(space0, tag("with"))(i)?;
+pub fn option_variable_bindings<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, VariableBindings, E> {
+    let (i, _) = preceded(space_or_comment0, tag("with"))(i)?;
     let (i, bindings) = variable_bindings(i)?;
     Ok((i, bindings))
 }
 
-fn variable_bindings<'a, E: ParseError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, VariableBindings, E> {
-    let (i, _) = preceded(space0, char('('))(i)?;
+fn variable_bindings<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, VariableBindings, E> {
+    let (i, _) = preceded(space_or_comment0, char('('))(i)?;
     let (i, bindings) = separated_list1(
         char(','),
-        alt((single_variable_binding, dual_variable_binding)),
+        alt((
+            single_variable_binding,
+            dual_variable_binding,
+            literal_variable_binding,
+            nested_variable_binding,
+            default_variable_binding,
+        )),
     )(i)?;
-    let (i, _) = preceded(space0, char(')'))(i)?;
+    let (i, _) = preceded(space_or_comment0, char(')'))(i)?;
     Ok((i, VariableBindings { bindings }))
 }
 
-fn dual_variable_binding<'a, E: ParseError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, VariableBinding, E> {
-    let (i, output) = preceded(space0, variable)(i)?;
-    let (i, _) = preceded(space0, char(':'))(i)?;
-    let (i, input) = preceded(space0, variable)(i)?;
+fn dual_variable_binding<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, VariableBinding, E> {
+    let (i, output) = preceded(space_or_comment0, variable)(i)?;
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let (i, input) = preceded(space_or_comment0, variable)(i)?;
     Ok((i, VariableBinding::Dual(output, input)))
 }
 
-fn single_variable_binding<'a, E: ParseError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, VariableBinding, E> {
-    let (i, input) = preceded(space0, variable)(i)?;
-    not(preceded(space0, char(':')))(i)?;
+fn literal_variable_binding<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, VariableBinding, E> {
+    let (i, output) = preceded(space_or_comment0, variable)(i)?;
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let (i, literal) = preceded(space_or_comment0, alt((string_literal, number_literal)))(i)?;
+    Ok((i, VariableBinding::Literal(output, literal)))
+}
+
+fn nested_variable_binding<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, VariableBinding, E> {
+    let (i, name) = preceded(space_or_comment0, variable)(i)?;
+    let (i, _) = preceded(space_or_comment0, char(':'))(i)?;
+    let (i, bindings) = variable_bindings(i)?;
+    Ok((i, VariableBinding::Nested(name, bindings)))
+}
+
+fn default_variable_binding<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, VariableBinding, E> {
+    let (i, name) = preceded(space_or_comment0, variable)(i)?;
+    let (i, _) = preceded(space_or_comment0, char('='))(i)?;
+    let (i, default) = preceded(space_or_comment0, string_literal)(i)?;
+    Ok((i, VariableBinding::Default(name, default)))
+}
+
+fn single_variable_binding<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, VariableBinding, E> {
+    let (i, input) = preceded(space_or_comment0, variable)(i)?;
+    not(preceded(space_or_comment0, one_of(":=")))(i)?;
     Ok((i, VariableBinding::Single(input)))
 }
 
-pub fn variable<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, String, E> {
+pub fn variable<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, String, E> {
     let (i, name) = recognize(pair(
         alt((alpha1, tag("_"), tag("-"))),
         many0_count(alt((alphanumeric1, tag("_"), tag("-")))),
     ))(i)?;
 
-    Ok((i, name.into()))
+    Ok((i, name.fragment().to_string()))
+}
+
+/// A number, which may be followed by a unit like in `5s`, usable unquoted as literal
+fn number_literal<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, String, E> {
+    let (i, number) = recognize(pair(digit1, alpha0))(i)?;
+    Ok((i, number.fragment().to_string()))
+}
+
+/// A double quoted string, in which `\\`, `\"` and `\n` are escaped
+pub fn string_literal<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, String, E> {
+    let (i, _) = char('"')(i)?;
+    let (i, content) = opt(escaped_transform(
+        none_of("\\\""),
+        '\\',
+        alt((
+            value("\\", char('\\')),
+            value("\"", char('"')),
+            value("\n", char('n')),
+        )),
+    ))(i)?;
+    let (i, _) = char('"')(i)?;
+    Ok((i, content.unwrap_or_default()))
+}
+
+/// A string literal or a bare `${...}` reference, which are both interpolated later on
+pub fn operand<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, String, E> {
+    preceded(
+        space_or_comment0,
+        alt((
+            string_literal,
+            map(
+                recognize(pair(tag("${"), pair(is_not("}"), char('}')))),
+                |reference: Input<'a>| reference.fragment().to_string(),
+            ),
+        )),
+    )(i)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use nom::error::Error;
+    use crate::parse::combinator::testing::parse_str;
 
     #[cfg(test)]
     mod variable {
@@ -78,13 +149,13 @@ mod tests {
 
         #[test]
         fn ok_simple() {
-            assert_eq!(variable::<Error<&str>>("x"), Ok(("", "x".into())));
+            assert_eq!(parse_str(variable, "x"), Ok(("", "x".into())));
         }
 
         #[test]
         fn ok_complex() {
             assert_eq!(
-                variable::<Error<&str>>("var_with_complex_name1234"),
+                parse_str(variable, "var_with_complex_name1234"),
                 Ok(("", "var_with_complex_name1234".into()))
             );
         }
@@ -92,28 +163,25 @@ mod tests {
         #[test]
         fn ok_complex_preceded_underscore() {
             assert_eq!(
-                variable::<Error<&str>>("_var_with_complex_name"),
+                parse_str(variable, "_var_with_complex_name"),
                 Ok(("", "_var_with_complex_name".into()))
             );
         }
 
         #[test]
         fn nok_preceded_number() {
-            assert!(variable::<Error<&str>>("1x").is_err());
+            assert!(parse_str(variable, "1x").is_err());
         }
 
         #[test]
         fn nok_special_char1() {
-            assert_eq!(
-                variable::<Error<&str>>("var-name"),
-                Ok(("", "var-name".into()))
-            );
+            assert_eq!(parse_str(variable, "var-name"), Ok(("", "var-name".into())));
         }
 
         #[test]
         fn nok_special_char2() {
             assert_ne!(
-                variable::<Error<&str>>("var()name"),
+                parse_str(variable, "var()name"),
                 Ok(("", "var()name".into()))
             );
         }
@@ -126,7 +194,7 @@ mod tests {
         #[test]
         fn ok_simple() {
             assert_eq!(
-                single_variable_binding::<Error<&str>>("x"),
+                parse_str(single_variable_binding, "x"),
                 Ok(("", "x".into()))
             );
         }
@@ -134,7 +202,7 @@ mod tests {
         #[test]
         fn ok_preceded_spaces() {
             assert_eq!(
-                single_variable_binding::<Error<&str>>("  x"),
+                parse_str(single_variable_binding, "  x"),
                 Ok(("", "x".into()))
             );
         }
@@ -142,7 +210,7 @@ mod tests {
         #[test]
         fn nok_dual_binding() {
             assert_ne!(
-                single_variable_binding::<Error<&str>>("x: y"),
+                parse_str(single_variable_binding, "x: y"),
                 Ok(("", ("x", "y").into()))
             );
         }
@@ -150,7 +218,7 @@ mod tests {
         #[test]
         fn nok_double_dot() {
             assert_ne!(
-                single_variable_binding::<Error<&str>>("x:"),
+                parse_str(single_variable_binding, "x:"),
                 Ok(("", "x".into()))
             );
         }
@@ -163,7 +231,7 @@ mod tests {
         #[test]
         fn ok_simple() {
             assert_eq!(
-                dual_variable_binding::<Error<&str>>("x: y"),
+                parse_str(dual_variable_binding, "x: y"),
                 Ok(("", ("x", "y").into()))
             );
         }
@@ -171,24 +239,142 @@ mod tests {
         #[test]
         fn ok_preceded_spaces() {
             assert_eq!(
-                dual_variable_binding::<Error<&str>>("  x :  y"),
+                parse_str(dual_variable_binding, "  x :  y"),
                 Ok(("", ("x", "y").into()))
             );
         }
 
         #[test]
         fn nok_single_binding() {
-            assert!(dual_variable_binding::<Error<&str>>("x").is_err());
+            assert!(parse_str(dual_variable_binding, "x").is_err());
         }
 
         #[test]
         fn nok_missing_right_side() {
-            assert!(dual_variable_binding::<Error<&str>>("x:").is_err());
+            assert!(parse_str(dual_variable_binding, "x:").is_err());
+        }
+
+        #[test]
+        fn ok_nested() {
+            assert_eq!(
+                parse_str(
+                    option_variable_bindings,
+                    "with (shell, env: ( KEY: var, OTHER: \"lit\", HOME ))"
+                ),
+                Ok((
+                    "",
+                    VariableBindings {
+                        bindings: vec!(
+                            "shell".into(),
+                            VariableBinding::Nested(
+                                "env".into(),
+                                VariableBindings {
+                                    bindings: vec!(
+                                        ("KEY", "var").into(),
+                                        VariableBinding::Literal("OTHER".into(), "lit".into()),
+                                        "HOME".into()
+                                    )
+                                }
+                            )
+                        )
+                    }
+                ))
+            );
+        }
+
+        #[test]
+        fn nok_nested_empty() {
+            assert!(parse_str(option_variable_bindings, "with (env: ())").is_err());
         }
 
         #[test]
         fn nok_missing_double_dot() {
-            assert!(dual_variable_binding::<Error<&str>>("x y").is_err());
+            assert!(parse_str(dual_variable_binding, "x y").is_err());
+        }
+    }
+
+    #[cfg(test)]
+    mod literal_variable_binding {
+        use super::*;
+
+        #[test]
+        fn ok_simple() {
+            assert_eq!(
+                parse_str(literal_variable_binding, "x: \"0,1\""),
+                Ok(("", VariableBinding::Literal("x".into(), "0,1".into())))
+            );
+        }
+
+        #[test]
+        fn ok_empty() {
+            assert_eq!(
+                parse_str(literal_variable_binding, "x: \"\""),
+                Ok(("", VariableBinding::Literal("x".into(), "".into())))
+            );
+        }
+
+        #[test]
+        fn ok_escaped() {
+            assert_eq!(
+                parse_str(literal_variable_binding, r#"x: "a \"b\" \\ c""#),
+                Ok((
+                    "",
+                    VariableBinding::Literal("x".into(), r#"a "b" \ c"#.into())
+                ))
+            );
+        }
+
+        #[test]
+        fn ok_number() {
+            assert_eq!(
+                parse_str(literal_variable_binding, "x: 3"),
+                Ok(("", VariableBinding::Literal("x".into(), "3".into())))
+            );
+            assert_eq!(
+                parse_str(literal_variable_binding, "x: 500ms"),
+                Ok(("", VariableBinding::Literal("x".into(), "500ms".into())))
+            );
+        }
+
+        #[test]
+        fn nok_unclosed() {
+            assert!(parse_str(literal_variable_binding, "x: \"0,1").is_err());
+        }
+
+        #[test]
+        fn nok_variable() {
+            assert!(parse_str(literal_variable_binding, "x: y").is_err());
+        }
+    }
+
+    #[cfg(test)]
+    mod default_variable_binding {
+        use super::*;
+
+        #[test]
+        fn ok_simple() {
+            assert_eq!(
+                parse_str(default_variable_binding, "env = \"staging\""),
+                Ok(("", VariableBinding::Default("env".into(), "staging".into())))
+            );
+        }
+
+        #[test]
+        fn ok_no_spaces() {
+            assert_eq!(
+                parse_str(default_variable_binding, "env=\"\""),
+                Ok(("", VariableBinding::Default("env".into(), "".into())))
+            );
+        }
+
+        #[test]
+        fn nok_variable() {
+            assert!(parse_str(default_variable_binding, "env = other").is_err());
+        }
+
+        #[test]
+        fn nok_single_binding() {
+            assert!(parse_str(single_variable_binding, "env = \"staging\"").is_err());
         }
     }
 
@@ -199,7 +385,7 @@ mod tests {
         #[test]
         fn ok_simple() {
             assert_eq!(
-                output_variable_bindings::<Error<&str>>("let (x: y) from"),
+                parse_str(output_variable_bindings, "let (x: y) from"),
                 Ok((
                     "",
                     VariableBindings {
@@ -212,7 +398,7 @@ mod tests {
         #[test]
         fn ok_spaced() {
             assert_eq!(
-                output_variable_bindings::<Error<&str>>("let   (  x  :  y)     from"),
+                parse_str(output_variable_bindings, "let   (  x  :  y)     from"),
                 Ok((
                     "",
                     VariableBindings {
@@ -225,7 +411,7 @@ mod tests {
         #[test]
         fn ok_no_spaces() {
             assert_eq!(
-                output_variable_bindings::<Error<&str>>("let(x:y)from"),
+                parse_str(output_variable_bindings, "let(x:y)from"),
                 Ok((
                     "",
                     VariableBindings {
@@ -238,7 +424,8 @@ mod tests {
         #[test]
         fn ok_multiple() {
             assert_eq!(
-                output_variable_bindings::<Error<&str>>(
+                parse_str(
+                    output_variable_bindings,
                     "let (x: y, stdout, stderr: input_var) from"
                 ),
                 Ok((
@@ -257,7 +444,7 @@ mod tests {
         #[test]
         fn nok_missing_double_dot() {
             assert_ne!(
-                output_variable_bindings::<Error<&str>>("let (x y) from"),
+                parse_str(output_variable_bindings, "let (x y) from"),
                 Ok((
                     "",
                     VariableBindings {
@@ -270,7 +457,7 @@ mod tests {
         #[test]
         fn nok_missing_brackets() {
             assert_ne!(
-                output_variable_bindings::<Error<&str>>("let (x: y from"),
+                parse_str(output_variable_bindings, "let (x: y from"),
                 Ok((
                     "",
                     VariableBindings {
@@ -283,7 +470,7 @@ mod tests {
         #[test]
         fn nok_missing_from() {
             assert_ne!(
-                output_variable_bindings::<Error<&str>>("let (x: y)"),
+                parse_str(output_variable_bindings, "let (x: y)"),
                 Ok((
                     "",
                     VariableBindings {
@@ -301,7 +488,7 @@ mod tests {
         #[test]
         fn ok_simple() {
             assert_eq!(
-                option_variable_bindings::<Error<&str>>("with (x: y)"),
+                parse_str(option_variable_bindings, "with (x: y)"),
                 Ok((
                     "",
                     VariableBindings {
@@ -314,7 +501,7 @@ mod tests {
         #[test]
         fn ok_spaced() {
             assert_eq!(
-                option_variable_bindings::<Error<&str>>("with   (  x  :  y)"),
+                parse_str(option_variable_bindings, "with   (  x  :  y)"),
                 Ok((
                     "",
                     VariableBindings {
@@ -327,7 +514,10 @@ mod tests {
         #[test]
         fn ok_multiple() {
             assert_eq!(
-                option_variable_bindings::<Error<&str>>("with (x: y, stdout, stderr: input_var)"),
+                parse_str(
+                    option_variable_bindings,
+                    "with (x: y, stdout, stderr: input_var)"
+                ),
                 Ok((
                     "",
                     VariableBindings {
@@ -341,10 +531,49 @@ mod tests {
             );
         }
 
+        #[test]
+        fn ok_literal() {
+            assert_eq!(
+                parse_str(
+                    option_variable_bindings,
+                    "with (allow_status: \"0,1\", silent)"
+                ),
+                Ok((
+                    "",
+                    VariableBindings {
+                        bindings: vec!(
+                            VariableBinding::Literal("allow_status".into(), "0,1".into()),
+                            "silent".into()
+                        )
+                    }
+                ))
+            );
+        }
+
+        #[test]
+        fn ok_defaults() {
+            assert_eq!(
+                parse_str(
+                    option_variable_bindings,
+                    "with (env = \"staging\", region, verbose = \"false\")"
+                ),
+                Ok((
+                    "",
+                    VariableBindings {
+                        bindings: vec!(
+                            VariableBinding::Default("env".into(), "staging".into()),
+                            "region".into(),
+                            VariableBinding::Default("verbose".into(), "false".into())
+                        )
+                    }
+                ))
+            );
+        }
+
         #[test]
         fn nok_missing_double_dot() {
             assert_ne!(
-                option_variable_bindings::<Error<&str>>("with (x y)"),
+                parse_str(option_variable_bindings, "with (x y)"),
                 Ok((
                     "",
                     VariableBindings {
@@ -357,7 +586,7 @@ mod tests {
         #[test]
         fn nok_missing_brackets() {
             assert_ne!(
-                option_variable_bindings::<Error<&str>>("with (x: y"),
+                parse_str(option_variable_bindings, "with (x: y"),
                 Ok((
                     "",
                     VariableBindings {
@@ -370,7 +599,7 @@ mod tests {
         #[test]
         fn nok_missing_with() {
             assert_ne!(
-                option_variable_bindings::<Error<&str>>("(x: y)"),
+                parse_str(option_variable_bindings, "(x: y)"),
                 Ok((
                     "",
                     VariableBindings {
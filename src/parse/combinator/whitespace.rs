@@ -0,0 +1,82 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_until},
+    character::complete::{multispace1, not_line_ending, space1},
+    combinator::recognize,
+    error::ParseError,
+    multi::many0_count,
+    sequence::{delimited, preceded},
+    IResult,
+};
+
+use crate::parse::span::Input;
+
+/// A `#` or `//` comment up to (excluding) the end of the line
+fn line_comment<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Input<'a>, E> {
+    recognize(preceded(alt((tag("#"), tag("//"))), not_line_ending))(i)
+}
+
+/// A `/* */` comment, which may span multiple lines
+fn block_comment<'a, E: ParseError<Input<'a>>>(i: Input<'a>) -> IResult<Input<'a>, Input<'a>, E> {
+    recognize(delimited(tag("/*"), take_until("*/"), tag("*/")))(i)
+}
+
+/// Like `multispace0`, but also consumes comments
+pub fn multispace_or_comment0<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Input<'a>, E> {
+    recognize(many0_count(alt((multispace1, line_comment, block_comment))))(i)
+}
+
+/// Like `space0`, but also consumes comments
+pub fn space_or_comment0<'a, E: ParseError<Input<'a>>>(
+    i: Input<'a>,
+) -> IResult<Input<'a>, Input<'a>, E> {
+    recognize(many0_count(alt((space1, line_comment, block_comment))))(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::combinator::testing::parse_str;
+
+    #[test]
+    fn ok_multispace_line_comments() {
+        assert_eq!(
+            parse_str(multispace_or_comment0, " # hash\n\t// slashes\n  run").map(|(i, _)| i),
+            Ok("run")
+        );
+    }
+
+    #[test]
+    fn ok_multispace_block_comment() {
+        assert_eq!(
+            parse_str(multispace_or_comment0, "/* multi\nline; */ \nrun").map(|(i, _)| i),
+            Ok("run")
+        );
+    }
+
+    #[test]
+    fn ok_multispace_empty() {
+        assert_eq!(
+            parse_str(multispace_or_comment0, "run").map(|(i, _)| i),
+            Ok("run")
+        );
+    }
+
+    #[test]
+    fn ok_space_stops_at_newline() {
+        assert_eq!(
+            parse_str(space_or_comment0, " /* c */ # comment\nrun").map(|(i, _)| i),
+            Ok("\nrun")
+        );
+    }
+
+    #[test]
+    fn nok_unclosed_block_comment() {
+        assert_eq!(
+            parse_str(multispace_or_comment0, "/* unclosed\nrun").map(|(i, _)| i),
+            Ok("/* unclosed\nrun")
+        );
+    }
+}
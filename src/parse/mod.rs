@@ -1,13 +1,17 @@
-use anyhow;
 use std::io;
 use std::{fs::File, io::Read};
 use thiserror::Error;
 
-use nom::error::{convert_error, VerboseError};
+use nom::error::{ErrorKind, VerboseError, VerboseErrorKind};
 use nom::Err;
 
+use crate::diagnostic::SpannedError;
+
+use self::span::{Input, Source, Span};
+
 pub mod ast;
 mod combinator;
+pub mod span;
 
 #[derive(Error, Debug)]
 pub enum ParseError {
@@ -30,11 +34,34 @@ pub fn load_and_parse(path: &str) -> anyhow::Result<ast::Namespace> {
         Err(e) => return Err(ParseError::InvalidFileContent(path.into(), e).into()),
     };
 
-    match combinator::namespace::module::<VerboseError<&str>>(&content) {
+    parse(path, content)
+}
+
+/// Parses the content of a module, with `path` only being used to report locations
+pub fn parse(path: &str, content: String) -> anyhow::Result<ast::Namespace> {
+    let source = Source::new(path.into(), content);
+    let input = Input::new_extra(&source.content, Some(&source));
+
+    match combinator::namespace::module::<VerboseError<Input>>(input) {
         Ok((_, ast)) => Ok(ast),
-        Err(Err::Error(e)) | Err(Err::Failure(e)) => {
-            Err(ParseError::SyntaxError(convert_error(content.as_str(), e)).into())
-        }
+        Err(Err::Error(e)) | Err(Err::Failure(e)) => Err(syntax_error(e)),
         Err(e) => panic!("invalid state! incomplete: {:?}", e),
     }
 }
+
+/// Converts the innermost error of the parser into a syntax error pointing at its location
+fn syntax_error(error: VerboseError<Input>) -> anyhow::Error {
+    let (input, kind) = &error.errors[0];
+    let message = match kind {
+        VerboseErrorKind::Char(expected) => format!("expected '{}'", expected),
+        VerboseErrorKind::Context(context) => format!("invalid {}", context),
+        VerboseErrorKind::Nom(ErrorKind::Eof) => "unexpected input".into(),
+        VerboseErrorKind::Nom(kind) => format!("unexpected input, expected {}", kind.description()),
+    };
+    let length = input.fragment().chars().next().map_or(0, char::len_utf8);
+    SpannedError::wrap(
+        Span::at(input, length),
+        message.clone(),
+        ParseError::SyntaxError(message),
+    )
+}
@@ -0,0 +1,173 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use nom_locate::LocatedSpan;
+
+/// A loaded task file, which is shared by all spans pointing into it
+#[derive(Debug, PartialEq, Eq)]
+pub struct Source {
+    pub path: String,
+    pub content: String,
+}
+
+impl Source {
+    pub fn new(path: String, content: String) -> Arc<Self> {
+        Arc::new(Self { path, content })
+    }
+}
+
+/// The input of all combinators, which tracks its location in the (optional) source
+pub type Input<'a> = LocatedSpan<&'a str, Option<&'a Arc<Source>>>;
+
+/// The location of an ast element in its source.
+///
+/// Spans are metadata only, two elements at different locations still compare and hash equal.
+#[derive(Clone, Default)]
+pub struct Span {
+    pub source: Option<Arc<Source>>,
+    pub offset: usize,
+    pub length: usize,
+    pub line: u32,
+    pub column: usize,
+}
+
+impl Span {
+    /// Creates the span from the start of `start` up to the start of `end`
+    pub fn between(start: &Input, end: &Input) -> Self {
+        Self {
+            source: start.extra.cloned(),
+            offset: start.location_offset(),
+            length: end.location_offset() - start.location_offset(),
+            line: start.location_line(),
+            column: start.get_utf8_column(),
+        }
+    }
+
+    /// Creates the span covering `length` bytes from the start of `start`
+    pub fn at(start: &Input, length: usize) -> Self {
+        Self {
+            source: start.extra.cloned(),
+            offset: start.location_offset(),
+            length,
+            line: start.location_line(),
+            column: start.get_utf8_column(),
+        }
+    }
+
+    /// Narrows the span to `length` bytes starting `offset` bytes into it
+    pub fn narrow(&self, offset: usize, length: usize) -> Self {
+        let before = self
+            .source
+            .as_ref()
+            .and_then(|source| source.content.get(..self.offset + offset));
+        let (line, column) = match before {
+            Some(before) => {
+                let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+                (
+                    before.matches('\n').count() as u32 + 1,
+                    before[line_start..].chars().count() + 1,
+                )
+            }
+            None => (self.line, self.column + offset),
+        };
+        Self {
+            source: self.source.clone(),
+            offset: self.offset + offset,
+            length,
+            line,
+            column,
+        }
+    }
+
+    /// The source text the span covers
+    pub fn text(&self) -> Option<&str> {
+        self.source
+            .as_ref()
+            .and_then(|source| source.content.get(self.offset..self.offset + self.length))
+    }
+
+    /// Narrows the span to its last `length` bytes
+    pub fn tail(&self, length: usize) -> Self {
+        self.narrow(self.length.saturating_sub(length), length.min(self.length))
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Span {}
+
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{}:{}:{}", source.path, self.line, self.column),
+            None => write!(f, "<unknown>:{}:{}", self.line, self.column),
+        }
+    }
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Span({}, {}..{})",
+            self,
+            self.offset,
+            self.offset + self.length
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::Slice;
+
+    use super::*;
+
+    #[test]
+    fn ok_between() {
+        let source = Source::new("test.inst".into(), "module as test;\nrun: x;".into());
+        let input = Input::new_extra(&source.content, Some(&source));
+
+        let span = Span::between(&input.slice(16..), &input.slice(22..));
+
+        assert_eq!(
+            (span.offset, span.length, span.line, span.column),
+            (16, 6, 2, 1)
+        );
+        assert_eq!(span.to_string(), "test.inst:2:1");
+    }
+
+    #[test]
+    fn ok_narrow_next_line() {
+        let source = Source::new("test.inst".into(), "run: {\n  echo ${x};\n};".into());
+        let input = Input::new_extra(&source.content, Some(&source));
+
+        let span = Span::at(&input, source.content.len()).narrow(14, 4);
+
+        assert_eq!(
+            (span.offset, span.length, span.line, span.column),
+            (14, 4, 2, 8)
+        );
+    }
+
+    #[test]
+    fn ok_tail() {
+        let source = Source::new("test.inst".into(), "run: echo ${x}".into());
+        let input = Input::new_extra(&source.content, Some(&source));
+
+        let span = Span::at(&input, source.content.len()).tail(9);
+
+        assert_eq!((span.offset, span.length, span.column), (5, 9, 6));
+    }
+}
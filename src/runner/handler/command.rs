@@ -1,70 +1,853 @@
 use std::{
-    process::{Command, Stdio},
-    str::from_utf8,
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    os::unix::process::{CommandExt, ExitStatusExt},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use crate::runner::message::{
-    action::{CreateAction, RunAction},
-    result::RunResult,
+    action::{CreateAction, Encoding, RunAction},
+    result::{Exit, RunResult, Stream, TIMED_OUT_CODE},
     RunnerAction, RunnerResponse,
 };
+use crate::util::interrupt::Interrupt;
 
-use super::{DynRunnerHandler, RunnerHandler};
+use super::{DynRunnerHandler, Responder, RunnerHandler};
 
-pub struct CommandHandler;
+const DEFAULT_SHELL: &str = "sh -c";
+
+/// The time a timed out or interrupted process group has to exit after it was signaled, before it
+/// is killed
+const TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Reads the pipe to its end in its own thread, so a process can not block on a full pipe, while
+/// every line is sent as soon as it was read
+fn read_in_background(
+    pipe: Option<impl Read + Send + 'static>,
+    stream: Stream,
+    encoding: Encoding,
+    responder: Responder,
+) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut content = Vec::new();
+        let mut reader = match pipe {
+            Some(pipe) => BufReader::new(pipe),
+            None => return content,
+        };
+        loop {
+            let start = content.len();
+            // Whatever was read until the pipe broke is kept
+            match reader.read_until(b'\n', &mut content) {
+                Ok(0) | Err(_) => return content,
+                Ok(_) => {
+                    let line = decode(content[start..].to_vec(), encoding, false);
+                    let line = line.trim_end_matches(&['\n', '\r'][..]).into();
+                    // The interpreter is free to stop listening, the output is captured anyway
+                    let _ = responder.send(RunnerResponse::Line(stream, line));
+                }
+            }
+        }
+    })
+}
+
+/// Waits for the process to exit, returning `None` if it is still running at the deadline
+fn wait_until(process: &mut Child, deadline: Instant) -> io::Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = process.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn signal_group(process: &Child, group: bool, signal: libc::c_int) {
+    let pid = process.id() as libc::pid_t;
+    // A process leading its own group is addressed with all of its children by the negated id
+    unsafe {
+        libc::kill(if group { -pid } else { pid }, signal);
+    }
+}
+
+/// Waits for the remaining processes of the group of an exited process, returning whether any of
+/// them is still running at the deadline
+fn wait_group_until(process: &Child, deadline: Instant) -> bool {
+    let pid = process.id() as libc::pid_t;
+    // Signal 0 only checks whether there is any process left in the group
+    while unsafe { libc::kill(-pid, 0) } == 0 {
+        if Instant::now() >= deadline {
+            return true;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    false
+}
+
+/// Waits for the process to exit, returning `None` if it was terminated after the timeout. A signal
+/// received by `inst` is forwarded to the process group, except for `SIGINT` if the process shares
+/// the terminal and the process group of `inst`, as the terminal sent it to the process as well
+fn wait(
+    process: &mut Child,
+    group: bool,
+    timeout: Option<Duration>,
+    interrupt: &Interrupt,
+) -> io::Result<Option<ExitStatus>> {
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    loop {
+        if !group {
+            // A Ctrl-C while attached to the terminal is meant for the process
+            interrupt.clear(libc::SIGINT);
+        }
+        if let Some(status) = process.try_wait()? {
+            return Ok(Some(status));
+        }
+        if let Some(signal) = interrupt.pending() {
+            return terminate(process, group, signal).map(Some);
+        }
+        if matches!(deadline, Some(deadline) if Instant::now() >= deadline) {
+            terminate(process, group, libc::SIGTERM)?;
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn exit(exit_status: ExitStatus) -> Exit {
+    match (exit_status.code(), exit_status.signal()) {
+        (Some(code), _) => Exit::Code(code),
+        (None, Some(signal)) => Exit::Signal(signal),
+        // Waiting only returns for processes that exited or were terminated by a signal
+        (None, None) => Exit::Code(-1),
+    }
+}
+
+/// Converts the output of a process to text, which is trimmed of surrounding whitespace if requested
+fn decode(content: Vec<u8>, encoding: Encoding, trim: bool) -> String {
+    match encoding {
+        Encoding::Lossy => {
+            let content = match String::from_utf8(content) {
+                Ok(content) => content,
+                Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+            };
+            match trim {
+                true => content.trim().into(),
+                false => content,
+            }
+        }
+        Encoding::Raw => {
+            let content: String = content.into_iter().map(char::from).collect();
+            // Only ascii whitespace is trimmed, as other whitespace characters stand for raw bytes
+            match trim {
+                true => content
+                    .trim_matches(|c: char| c.is_ascii_whitespace())
+                    .into(),
+                false => content,
+            }
+        }
+    }
+}
+
+/// Converts text to the input of a process, the reverse of `decode`
+fn encode(content: &str, encoding: Encoding) -> Vec<u8> {
+    match encoding {
+        Encoding::Lossy => content.as_bytes().to_vec(),
+        Encoding::Raw => content
+            .chars()
+            .flat_map(|c| match u8::try_from(c) {
+                Ok(byte) => vec![byte],
+                Err(_) => c.to_string().into_bytes(),
+            })
+            .collect(),
+    }
+}
+
+/// Sends the signal to the process group, which is killed if it does not exit within the grace period
+fn terminate(process: &mut Child, group: bool, signal: libc::c_int) -> io::Result<ExitStatus> {
+    signal_group(process, group, signal);
+    let deadline = Instant::now() + TERMINATION_GRACE_PERIOD;
+    if let Some(status) = wait_until(process, deadline)? {
+        // The other processes of the group get the rest of the grace period, while the id of an
+        // exited process that led no group might already be reused
+        if group && wait_group_until(process, deadline) {
+            signal_group(process, group, libc::SIGKILL);
+        }
+        return Ok(status);
+    }
+    signal_group(process, group, libc::SIGKILL);
+    process.wait()
+}
+
+pub struct CommandHandler {
+    shell: Vec<String>,
+    use_shell: bool,
+    interrupt: Interrupt,
+}
 
 impl CommandHandler {
-    pub fn new() -> DynRunnerHandler {
-        Box::new(Self)
+    pub fn create(args: &HashMap<String, String>) -> Result<DynRunnerHandler, RunnerResponse> {
+        let shell_arg = args
+            .get("shell")
+            .map(|val| val.as_str())
+            .unwrap_or(DEFAULT_SHELL);
+        let shell = match shlex::split(shell_arg) {
+            Some(shell) if !shell.is_empty() => shell,
+            _ => {
+                return Err(RunnerResponse::InvalidRunnerArgument(
+                    "shell".into(),
+                    shell_arg.into(),
+                ))
+            }
+        };
+
+        let use_shell = match args.get("use_shell").map(|val| val.as_str()) {
+            Some("true") => true,
+            Some("false") | None => false,
+            Some(other) => {
+                return Err(RunnerResponse::InvalidRunnerArgument(
+                    "use_shell".into(),
+                    other.into(),
+                ))
+            }
+        };
+
+        Ok(Box::new(Self {
+            shell,
+            use_shell,
+            interrupt: Interrupt::global(),
+        }))
     }
 
-    fn handle_run_action(&self, run_action: RunAction) -> RunnerResponse {
-        let command = &run_action.command;
-        let mut cmd_iter = command.split(' ');
-        let program = cmd_iter.next().unwrap();
-        let args: Vec<&str> = cmd_iter.collect();
+    /// Builds the process either by passing the whole command to the shell or by splitting it into posix shell words
+    fn build_command(&self, run_action: &RunAction) -> Option<Command> {
+        if run_action.use_shell.unwrap_or(self.use_shell) {
+            let mut cmd = Command::new(&self.shell[0]);
+            cmd.args(&self.shell[1..]);
+            cmd.arg(&run_action.command);
+            Some(cmd)
+        } else {
+            let words = shlex::split(&run_action.command)?;
+            let (program, args) = words.split_first()?;
+            let mut cmd = Command::new(program);
+            cmd.args(args);
+            Some(cmd)
+        }
+    }
 
-        let mut cmd = Command::new(program);
-        cmd.args(args);
-        cmd.stdin(Stdio::piped());
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
+    fn handle_run_action(&self, run_action: RunAction, responder: &Responder) -> RunnerResponse {
+        let command = &run_action.command;
+        let mut cmd = match self.build_command(&run_action) {
+            Some(cmd) => cmd,
+            None => return RunnerResponse::InvalidCommand(command.into()),
+        };
+        cmd.envs(&run_action.env);
+        if let Some(cwd) = &run_action.cwd {
+            if !cwd.is_dir() {
+                return RunnerResponse::WorkingDirectoryNotFound(cwd.display().to_string());
+            }
+            cmd.current_dir(cwd);
+        }
+        // Without pipes there is nothing to capture, which the readers and writer below handle
+        // as they are given no pipe
+        let stdio = || match run_action.interactive {
+            true => Stdio::inherit(),
+            false => Stdio::piped(),
+        };
+        cmd.stdin(stdio());
+        cmd.stdout(stdio());
+        cmd.stderr(stdio());
+        // Its own process group allows to signal the children of the process as well, which is not
+        // possible for an interactive one, as only the foreground group may read the terminal
+        let group = !run_action.interactive;
+        if group {
+            cmd.process_group(0);
+        }
 
-        let process = match cmd.spawn() {
+        let mut process = match cmd.spawn() {
             Ok(child) => child,
             Err(_) => return RunnerResponse::CommandNotFound(command.into()),
         };
 
-        let output = process.wait_with_output().unwrap();
-        let mut stdout: String = from_utf8(&output.stdout).unwrap().into();
-        if run_action.trim_stdout {
-            stdout = stdout.trim().into();
+        // Writing happens in its own thread, as the process might block on a full stdout before reading all of stdin
+        let stdin_writer = match (process.stdin.take(), run_action.stdin) {
+            (Some(mut stdin), Some(content)) => {
+                let content = encode(&content, run_action.encoding);
+                Some(thread::spawn(move || {
+                    // The process is free to exit before consuming its whole input
+                    let _ = stdin.write_all(&content);
+                }))
+            }
+            _ => None,
+        };
+
+        let stdout_reader = read_in_background(
+            process.stdout.take(),
+            Stream::Stdout,
+            run_action.encoding,
+            responder.clone(),
+        );
+        let stderr_reader = read_in_background(
+            process.stderr.take(),
+            Stream::Stderr,
+            run_action.encoding,
+            responder.clone(),
+        );
+
+        let exit_status = match wait(&mut process, group, run_action.timeout, &self.interrupt) {
+            Ok(exit_status) => exit_status,
+            Err(err) => return RunnerResponse::ProcessFailed(command.into(), err.to_string()),
+        };
+        if run_action.interactive {
+            // A Ctrl-C right before the process exited was still meant for it
+            self.interrupt.clear(libc::SIGINT);
         }
-        let mut stderr: String = from_utf8(&output.stderr).unwrap().into();
-        if run_action.trim_stderr {
-            stderr = stderr.trim().into();
+        if let Some(stdin_writer) = stdin_writer {
+            let _ = stdin_writer.join();
         }
-        let status: String = output.status.code().unwrap().to_string();
+        let stdout = decode(
+            stdout_reader.join().unwrap_or_default(),
+            run_action.encoding,
+            run_action.trim_stdout,
+        );
+        let stderr = decode(
+            stderr_reader.join().unwrap_or_default(),
+            run_action.encoding,
+            run_action.trim_stderr,
+        );
 
-        RunnerResponse::Output(RunResult {
-            stdout,
-            stderr,
-            status,
-        })
+        match exit_status {
+            Some(exit_status) => RunnerResponse::Output(RunResult {
+                stdout,
+                stderr,
+                exit: exit(exit_status),
+            }),
+            None => RunnerResponse::TimedOut(RunResult {
+                stdout,
+                stderr,
+                exit: Exit::Code(TIMED_OUT_CODE),
+            }),
+        }
     }
 
     fn handle_create_action(&self, _create_action: CreateAction) -> RunnerResponse {
-        return RunnerResponse::Created;
+        RunnerResponse::Created
     }
 }
 
 #[allow(unreachable_patterns)]
 impl RunnerHandler for CommandHandler {
-    fn handle(&mut self, action: RunnerAction) -> RunnerResponse {
+    fn handle(&mut self, action: RunnerAction, responder: &Responder) -> RunnerResponse {
         match action {
-            RunnerAction::Run(run_action) => self.handle_run_action(run_action),
+            RunnerAction::Run(run_action) => self.handle_run_action(run_action, responder),
             RunnerAction::Create(create_action) => self.handle_create_action(create_action),
             _ => panic!("received invalid action {:?}", &action),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A run of the command without any options, which the tests override as needed
+    fn action(command: &str) -> RunAction {
+        RunAction {
+            command: command.into(),
+            use_shell: None,
+            stdin: None,
+            env: HashMap::new(),
+            cwd: None,
+            timeout: None,
+            encoding: Encoding::Lossy,
+            interactive: false,
+            trim_stdout: true,
+            trim_stderr: true,
+        }
+    }
+
+    fn run_action(args: Vec<(&str, &str)>, run_action: RunAction) -> RunnerResponse {
+        let args = args
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        let mut handler = CommandHandler::create(&args).ok().unwrap();
+        let (responder, _) = crossbeam_channel::unbounded();
+        handler.handle(RunnerAction::Run(run_action), &responder)
+    }
+
+    fn run_in(cwd: &str, command: &str) -> RunnerResponse {
+        run_action(
+            vec![],
+            RunAction {
+                cwd: Some(cwd.into()),
+                ..action(command)
+            },
+        )
+    }
+
+    fn stdout(response: RunnerResponse) -> String {
+        match response {
+            RunnerResponse::Output(result) => result.stdout,
+            other => panic!("expected output, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ok_split_double_quotes() {
+        assert_eq!(
+            stdout(run_action(vec![], action(r#"echo "a   b""#))),
+            "a   b"
+        );
+    }
+
+    #[test]
+    fn ok_split_single_quotes_and_escapes() {
+        assert_eq!(
+            stdout(run_action(vec![], action(r#"echo 'a | b' c\ \ d"#))),
+            "a | b c  d"
+        );
+    }
+
+    #[test]
+    fn ok_split_no_pipe() {
+        assert_eq!(
+            stdout(run_action(vec![], action("echo a | wc -l"))),
+            "a | wc -l"
+        );
+    }
+
+    #[test]
+    fn ok_shell_pipe() {
+        assert_eq!(
+            stdout(run_action(
+                vec![],
+                RunAction {
+                    use_shell: Some(true),
+                    ..action("echo a | wc -l")
+                }
+            )),
+            "1"
+        );
+    }
+
+    #[test]
+    fn ok_shell_from_runner_args() {
+        assert_eq!(
+            stdout(run_action(
+                vec![("use_shell", "true")],
+                action("echo a && echo b")
+            )),
+            "a\nb"
+        );
+    }
+
+    #[test]
+    fn ok_shell_overwritten() {
+        assert_eq!(
+            stdout(run_action(
+                vec![("use_shell", "true")],
+                RunAction {
+                    use_shell: Some(false),
+                    ..action("echo a && echo b")
+                }
+            )),
+            "a && echo b"
+        );
+    }
+
+    #[test]
+    fn ok_custom_shell() {
+        assert_eq!(
+            stdout(run_action(
+                vec![("shell", "sh -c 'echo custom: $0'")],
+                RunAction {
+                    use_shell: Some(true),
+                    ..action("ignored")
+                }
+            )),
+            "custom: ignored"
+        );
+    }
+
+    #[test]
+    fn ok_stdin() {
+        assert_eq!(
+            stdout(run_action(
+                vec![],
+                RunAction {
+                    stdin: Some("used in main".into()),
+                    ..action("sed s/main/post/g")
+                }
+            )),
+            "used in post"
+        );
+    }
+
+    #[test]
+    fn ok_stdin_larger_than_pipe_buffer() {
+        let content = "x".repeat(1024 * 1024);
+        assert_eq!(
+            stdout(run_action(
+                vec![],
+                RunAction {
+                    stdin: Some(content.clone()),
+                    ..action("cat")
+                }
+            )),
+            content
+        );
+    }
+
+    #[test]
+    fn ok_stdin_not_consumed() {
+        let content = "x".repeat(1024 * 1024);
+        assert_eq!(
+            stdout(run_action(
+                vec![],
+                RunAction {
+                    stdin: Some(content),
+                    ..action("echo done")
+                }
+            )),
+            "done"
+        );
+    }
+
+    #[test]
+    fn ok_env() {
+        let env = vec![("INST_TEST_ENV".to_owned(), "a b".to_owned())]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            stdout(run_action(
+                vec![],
+                RunAction {
+                    use_shell: Some(true),
+                    env,
+                    ..action("echo \"$INST_TEST_ENV\"")
+                }
+            )),
+            "a b"
+        );
+    }
+
+    #[test]
+    fn ok_cwd() {
+        assert_eq!(stdout(run_in("/", "pwd")), "/");
+    }
+
+    fn run_with_timeout(command: &str, timeout: Duration) -> RunnerResponse {
+        run_action(
+            vec![],
+            RunAction {
+                use_shell: Some(true),
+                timeout: Some(timeout),
+                ..action(command)
+            },
+        )
+    }
+
+    #[test]
+    fn ok_timeout_not_reached() {
+        assert_eq!(
+            stdout(run_with_timeout("echo done", Duration::from_secs(5))),
+            "done"
+        );
+    }
+
+    #[test]
+    fn nok_timeout_partial_output() {
+        let started = Instant::now();
+        assert_eq!(
+            run_with_timeout("echo partial; sleep 5", Duration::from_millis(200)),
+            RunnerResponse::TimedOut(RunResult {
+                stdout: "partial".into(),
+                stderr: "".into(),
+                exit: Exit::Code(TIMED_OUT_CODE),
+            })
+        );
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn nok_timeout_terminates_children() {
+        // The background sleep keeps stdout open, so reading it only ends once it is terminated too
+        let started = Instant::now();
+        let response = run_with_timeout("sleep 5 & echo started; wait", Duration::from_millis(200));
+        assert!(matches!(response, RunnerResponse::TimedOut(_)));
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    fn run_with_encoding(command: &str, stdin: Option<&str>, encoding: Encoding) -> RunnerResponse {
+        run_action(
+            vec![],
+            RunAction {
+                use_shell: Some(true),
+                stdin: stdin.map(String::from),
+                encoding,
+                ..action(command)
+            },
+        )
+    }
+
+    #[test]
+    fn ok_signal() {
+        assert_eq!(
+            run_action(
+                vec![],
+                RunAction {
+                    use_shell: Some(true),
+                    ..action("echo killed; kill -9 $$")
+                }
+            ),
+            RunnerResponse::Output(RunResult {
+                stdout: "killed".into(),
+                stderr: "".into(),
+                exit: Exit::Signal(9),
+            })
+        );
+    }
+
+    #[test]
+    fn ok_invalid_utf8_lossy() {
+        assert_eq!(
+            stdout(run_with_encoding("printf 'a\\377'", None, Encoding::Lossy)),
+            "a\u{FFFD}"
+        );
+    }
+
+    #[test]
+    fn ok_raw() {
+        assert_eq!(
+            stdout(run_with_encoding("printf 'a\\377'", None, Encoding::Raw)),
+            "a\u{ff}"
+        );
+        assert_eq!(
+            stdout(run_with_encoding("wc -c", Some("a\u{ff}"), Encoding::Raw)),
+            "2"
+        );
+    }
+
+    #[test]
+    fn ok_lines_streamed() {
+        let mut handler = CommandHandler::create(&HashMap::new()).ok().unwrap();
+        let (responder, lines) = crossbeam_channel::unbounded();
+        let response = handler.handle(
+            RunnerAction::Run(RunAction {
+                use_shell: Some(true),
+                trim_stdout: false,
+                trim_stderr: false,
+                ..action("echo a; echo b >&2; printf 'c\\r\\nd'")
+            }),
+            &responder,
+        );
+
+        let lines: Vec<RunnerResponse> = lines.try_iter().collect();
+        let stream = |stream: Stream| -> Vec<String> {
+            lines
+                .iter()
+                .filter_map(|line| match line {
+                    RunnerResponse::Line(line_stream, line) if *line_stream == stream => {
+                        Some(line.clone())
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(stream(Stream::Stdout), vec!["a", "c", "d"]);
+        assert_eq!(stream(Stream::Stderr), vec!["b"]);
+        // The whole output is still captured
+        assert_eq!(stdout(response), "a\nc\r\nd");
+    }
+
+    fn run_interactive(command: &str, timeout: Option<Duration>) -> RunnerResponse {
+        run_action(
+            vec![],
+            RunAction {
+                use_shell: Some(true),
+                timeout,
+                interactive: true,
+                ..action(command)
+            },
+        )
+    }
+
+    #[test]
+    fn ok_interactive_not_captured() {
+        assert_eq!(
+            run_interactive("exit 3", None),
+            RunnerResponse::Output(RunResult {
+                stdout: "".into(),
+                stderr: "".into(),
+                exit: Exit::Code(3),
+            })
+        );
+    }
+
+    #[test]
+    fn nok_interactive_timeout() {
+        let started = Instant::now();
+        let response = run_interactive("sleep 5", Some(Duration::from_millis(200)));
+        assert!(matches!(response, RunnerResponse::TimedOut(_)));
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    fn run_interrupted(interrupt: Interrupt, run_action: RunAction) -> RunnerResponse {
+        let mut handler = CommandHandler {
+            shell: vec!["sh".into(), "-c".into()],
+            use_shell: true,
+            interrupt,
+        };
+        let (responder, _) = crossbeam_channel::unbounded();
+        handler.handle(RunnerAction::Run(run_action), &responder)
+    }
+
+    fn trigger_later(interrupt: &Interrupt, signal: libc::c_int) {
+        let interrupt = interrupt.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            interrupt.trigger(signal);
+        });
+    }
+
+    #[test]
+    fn ok_process_group_unless_interactive() {
+        // Field 1 and 5 of the stat of the shell are its process id and process group id
+        let ids = stdout(run_action(
+            vec![],
+            RunAction {
+                use_shell: Some(true),
+                ..action("cut -d' ' -f1,5 /proc/$$/stat")
+            },
+        ));
+        let ids: Vec<&str> = ids.split(' ').collect();
+        assert_eq!(ids[0], ids[1]);
+
+        let own_group = unsafe { libc::getpgrp() };
+        let command = format!("test $(cut -d' ' -f5 /proc/$$/stat) = {}", own_group);
+        assert!(matches!(
+            run_interrupted(
+                Interrupt::default(),
+                RunAction {
+                    interactive: true,
+                    ..action(&command)
+                }
+            ),
+            RunnerResponse::Output(RunResult {
+                exit: Exit::Code(0),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn nok_interrupt_forwarded_to_group() {
+        // Both ends of the pipe have to be signaled, as the shell waits for them
+        for signal in [libc::SIGTERM, libc::SIGINT] {
+            let interrupt = Interrupt::default();
+            trigger_later(&interrupt, signal);
+
+            let started = Instant::now();
+            let response = run_interrupted(interrupt, action("sleep 5 | cat"));
+            assert!(matches!(response, RunnerResponse::Output(_)));
+            assert!(started.elapsed() < Duration::from_secs(4));
+        }
+
+        // The background sleep keeps stdout open, so reading it only ends once it is killed too
+        let interrupt = Interrupt::default();
+        trigger_later(&interrupt, libc::SIGTERM);
+        let started = Instant::now();
+        assert_eq!(
+            run_interrupted(interrupt, action("sleep 5 & wait")),
+            RunnerResponse::Output(RunResult {
+                stdout: "".into(),
+                stderr: "".into(),
+                exit: Exit::Signal(libc::SIGTERM),
+            })
+        );
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn ok_group_cleans_up_after_leader_exited() {
+        let interrupt = Interrupt::default();
+        trigger_later(&interrupt, libc::SIGTERM);
+
+        let response = run_interrupted(
+            interrupt,
+            action("(trap 'sleep 0.3; echo cleaned; exit' TERM; while true; do sleep 0.05; done) & sleep 5"),
+        );
+        assert_eq!(stdout(response), "cleaned");
+    }
+
+    #[test]
+    fn ok_interactive_keeps_sigint() {
+        let interrupt = Interrupt::default();
+        interrupt.trigger(libc::SIGINT);
+
+        assert_eq!(
+            run_interrupted(
+                interrupt.clone(),
+                RunAction {
+                    interactive: true,
+                    ..action("sleep 0.2")
+                }
+            ),
+            RunnerResponse::Output(RunResult {
+                stdout: "".into(),
+                stderr: "".into(),
+                exit: Exit::Code(0),
+            })
+        );
+        assert_eq!(interrupt.pending(), None);
+    }
+
+    #[test]
+    fn nok_cwd_not_found() {
+        assert_eq!(
+            run_in("/not/existing", "pwd"),
+            RunnerResponse::WorkingDirectoryNotFound("/not/existing".into())
+        );
+    }
+
+    #[test]
+    fn nok_unclosed_quote() {
+        assert_eq!(
+            run_action(vec![], action(r#"echo "a"#)),
+            RunnerResponse::InvalidCommand(r#"echo "a"#.into())
+        );
+    }
+
+    #[test]
+    fn nok_invalid_shell() {
+        let args = vec![("shell".to_owned(), "".to_owned())]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            CommandHandler::create(&args).err(),
+            Some(RunnerResponse::InvalidRunnerArgument(
+                "shell".into(),
+                "".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn nok_invalid_use_shell() {
+        let args = vec![("use_shell".to_owned(), "yes".to_owned())]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            CommandHandler::create(&args).err(),
+            Some(RunnerResponse::InvalidRunnerArgument(
+                "use_shell".into(),
+                "yes".into()
+            ))
+        );
+    }
+}
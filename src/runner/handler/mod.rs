@@ -1,16 +1,24 @@
-use super::message::{RunnerAction, RunnerResponse};
+use crossbeam_channel::Sender;
+
+use super::message::{action::CreateAction, RunnerAction, RunnerResponse};
 
 mod command;
 
+/// Sends responses while an action is still being handled, like the lines of a running command
+pub type Responder = Sender<RunnerResponse>;
+
 pub trait RunnerHandler {
-    fn handle(&mut self, action: RunnerAction) -> RunnerResponse;
+    /// Handles the action, where the returned response is the final one for it
+    fn handle(&mut self, action: RunnerAction, responder: &Responder) -> RunnerResponse;
 }
 
 pub type DynRunnerHandler = Box<dyn RunnerHandler>;
 
-pub fn create_new(runner_type: &str) -> Option<DynRunnerHandler> {
-    match runner_type {
-        "command" => Some(command::CommandHandler::new()),
-        _ => None,
+pub fn create_new(create_action: &CreateAction) -> Result<DynRunnerHandler, RunnerResponse> {
+    match create_action.runner_type.as_str() {
+        "command" => command::CommandHandler::create(&create_action.args),
+        _ => Err(RunnerResponse::RunnerTypeNotExisting(
+            create_action.runner_type.clone(),
+        )),
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crossbeam_channel::{RecvError, SendError};
 use thiserror::Error;
 
@@ -10,11 +12,27 @@ pub enum RunnerInterfaceError {
     #[error("received invalid runner response {1:?} for action '{0:?}'")]
     InvalidResponse(&'static str, RunnerResponse),
     #[error("could not send message to channel: {0}")]
-    SendChannelError(SendError<RunnerRequest>),
+    SendChannelError(Box<SendError<RunnerRequest>>),
     #[error("could not receive message from channel: {0}")]
     ReceiveChannelError(RecvError),
     #[error("runner could not find command '{0}'")]
     CommandNotFound(String),
+    #[error("runner could not split command '{0}' into words, check its quotes and escapes")]
+    InvalidCommand(String),
+    #[error("working directory '{0}' does not exist")]
+    WorkingDirectoryNotFound(String),
+    #[error("runner '{0}' does not exist")]
+    RunnerNotExisting(String),
+    #[error("runner '{0}' already exists")]
+    RunnerAlreadyExists(String),
+    #[error("runner type '{0}' does not exist")]
+    RunnerTypeNotExisting(String),
+    #[error("invalid value '{1}' for runner argument '{0}'")]
+    InvalidRunnerArgument(String, String),
+    #[error("command timed out with {}", .0.exit)]
+    TimedOut(result::RunResult),
+    #[error("waiting for command '{0}' failed: {1}")]
+    ProcessFailed(String, String),
 }
 
 type RunnerInterfaceResult<T> = std::result::Result<T, RunnerInterfaceError>;
@@ -28,40 +46,96 @@ impl RunnerInterface {
         Self { channel }
     }
 
-    fn send_and_receive(&self, msg: RunnerRequest) -> RunnerInterfaceResult<RunnerResponse> {
+    fn send(&self, msg: RunnerRequest) -> RunnerInterfaceResult<()> {
         self.channel
             .tx
             .send(msg)
-            .map_err(|err| RunnerInterfaceError::SendChannelError(err))?;
-        Ok(self
-            .channel
+            .map_err(|err| RunnerInterfaceError::SendChannelError(Box::new(err)))
+    }
+
+    fn receive(&self) -> RunnerInterfaceResult<RunnerResponse> {
+        self.channel
             .rx
             .recv()
-            .map_err(|err| RunnerInterfaceError::ReceiveChannelError(err))?)
+            .map_err(RunnerInterfaceError::ReceiveChannelError)
+    }
+
+    fn send_and_receive(&self, msg: RunnerRequest) -> RunnerInterfaceResult<RunnerResponse> {
+        self.send(msg)?;
+        self.receive()
+    }
+
+    pub fn create(
+        &self,
+        runner_name: String,
+        runner_type: String,
+        args: HashMap<String, String>,
+    ) -> RunnerInterfaceResult<()> {
+        let msg = RunnerRequest {
+            runner_name: runner_name.clone(),
+            action: RunnerAction::Create(action::CreateAction {
+                runner_name,
+                runner_type,
+                args,
+            }),
+        };
+        let response = self.send_and_receive(msg)?;
+
+        match response {
+            RunnerResponse::Created => Ok(()),
+            RunnerResponse::RunnerAlreadyExists(name) => {
+                Err(RunnerInterfaceError::RunnerAlreadyExists(name))
+            }
+            RunnerResponse::RunnerTypeNotExisting(runner_type) => {
+                Err(RunnerInterfaceError::RunnerTypeNotExisting(runner_type))
+            }
+            RunnerResponse::InvalidRunnerArgument(name, value) => {
+                Err(RunnerInterfaceError::InvalidRunnerArgument(name, value))
+            }
+            other_response => Err(RunnerInterfaceError::InvalidResponse(
+                "create",
+                other_response,
+            )),
+        }
     }
 
+    /// Runs the command, passing every line it writes to `on_line` while it is running
     pub fn run(
         &self,
         runner_name: String,
-        command: String,
-        trim_stdout: bool,
-        trim_stderr: bool,
+        run_action: action::RunAction,
+        mut on_line: impl FnMut(result::Stream, &str),
     ) -> RunnerInterfaceResult<result::RunResult> {
         let msg = RunnerRequest {
             runner_name,
-            action: RunnerAction::Run(action::RunAction {
-                command,
-                trim_stdout,
-                trim_stderr,
-            }),
+            action: RunnerAction::Run(run_action),
+        };
+        self.send(msg)?;
+        let response = loop {
+            match self.receive()? {
+                RunnerResponse::Line(stream, line) => on_line(stream, &line),
+                response => break response,
+            }
         };
-        let response = self.send_and_receive(msg)?;
 
         match response {
             RunnerResponse::Output(output) => Ok(output),
+            RunnerResponse::TimedOut(output) => Err(RunnerInterfaceError::TimedOut(output)),
             RunnerResponse::CommandNotFound(command) => {
                 Err(RunnerInterfaceError::CommandNotFound(command))
             }
+            RunnerResponse::InvalidCommand(command) => {
+                Err(RunnerInterfaceError::InvalidCommand(command))
+            }
+            RunnerResponse::WorkingDirectoryNotFound(directory) => {
+                Err(RunnerInterfaceError::WorkingDirectoryNotFound(directory))
+            }
+            RunnerResponse::RunnerNotExisting(name) => {
+                Err(RunnerInterfaceError::RunnerNotExisting(name))
+            }
+            RunnerResponse::ProcessFailed(command, error) => {
+                Err(RunnerInterfaceError::ProcessFailed(command, error))
+            }
             other_response => Err(RunnerInterfaceError::InvalidResponse("run", other_response)),
         }
     }
@@ -5,15 +5,39 @@ pub struct RunnerRequest {
 }
 
 pub mod action {
-    use std::collections::HashMap;
+    use std::{collections::HashMap, path::PathBuf, time::Duration};
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct RunAction {
         pub command: String,
+        /// Whether the command is passed to the runners shell, `None` uses the runners default
+        pub use_shell: Option<bool>,
+        /// Content written to the stdin of the process, which is closed afterwards
+        pub stdin: Option<String>,
+        /// Environment variables set for the process in addition to the inherited ones
+        pub env: HashMap<String, String>,
+        /// The working directory of the process, `None` keeps the one of `inst`
+        pub cwd: Option<PathBuf>,
+        /// The time after which the process and its children are terminated
+        pub timeout: Option<Duration>,
+        /// How the output is converted to text
+        pub encoding: Encoding,
+        /// Whether the process is attached to the terminal of `inst`, where its output is not captured
+        pub interactive: bool,
         pub trim_stdout: bool,
         pub trim_stderr: bool,
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum Encoding {
+        /// Invalid utf-8 is replaced by `U+FFFD`
+        #[default]
+        Lossy,
+        /// Every byte is kept as the character of the same value, which is converted back to the
+        /// byte when written to stdin, so binary content can be passed between commands
+        Raw,
+    }
+
     #[derive(Debug)]
     pub struct CreateAction {
         pub runner_name: String,
@@ -29,20 +53,68 @@ pub enum RunnerAction {
 }
 
 pub mod result {
+    use std::fmt;
+
+    /// The exit code of a command that timed out, like the one of `timeout` of coreutils
+    pub const TIMED_OUT_CODE: i32 = 124;
+
+    /// How a process ended
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Exit {
+        Code(i32),
+        /// Terminated by the signal with the number
+        Signal(i32),
+    }
+
+    impl Exit {
+        /// The status as shells report it, where a terminating signal is `128` plus its number
+        pub fn status(&self) -> String {
+            match self {
+                Exit::Code(code) => code.to_string(),
+                Exit::Signal(signal) => (128 + signal).to_string(),
+            }
+        }
+    }
+
+    impl fmt::Display for Exit {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Exit::Code(code) => write!(f, "status {}", code),
+                Exit::Signal(signal) => write!(f, "signal {}", signal),
+            }
+        }
+    }
+
+    /// The output stream of a process
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Stream {
+        Stdout,
+        Stderr,
+    }
+
     #[derive(Debug, PartialEq)]
     pub struct RunResult {
         pub stdout: String,
         pub stderr: String,
-        pub status: String,
+        pub exit: Exit,
     }
 }
 
 #[derive(Debug, PartialEq)]
 pub enum RunnerResponse {
+    /// A line the running command wrote, which is sent before its final response
+    Line(result::Stream, String),
     Output(result::RunResult),
+    /// The command was terminated after its timeout, with the output it wrote until then
+    TimedOut(result::RunResult),
     Created,
     CommandNotFound(String),
+    InvalidCommand(String),
+    WorkingDirectoryNotFound(String),
     RunnerAlreadyExists(String),
     RunnerNotExisting(String),
     RunnerTypeNotExisting(String),
+    InvalidRunnerArgument(String, String),
+    /// Waiting for the process of the command failed with the error
+    ProcessFailed(String, String),
 }
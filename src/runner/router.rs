@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
 use super::{
-    handler::{create_new, DynRunnerHandler},
-    message::{action, RunnerAction, RunnerRequest, RunnerResponse},
+    handler::{create_new, DynRunnerHandler, Responder},
+    message::{RunnerAction, RunnerRequest, RunnerResponse},
 };
 
 pub struct Router {
@@ -11,35 +11,24 @@ pub struct Router {
 
 impl Router {
     pub fn new() -> Self {
-        let mut router = Self {
+        Self {
             handlers: HashMap::new(),
-        };
-
-        let result = router.handle_request(RunnerRequest {
-            runner_name: "default".into(),
-            action: RunnerAction::Create(action::CreateAction {
-                runner_name: "default".into(),
-                runner_type: "command".into(),
-                args: HashMap::new(),
-            }),
-        });
-
-        assert_eq!(result, RunnerResponse::Created);
-
-        router
+        }
     }
 
-    pub fn handle_request(&mut self, request: RunnerRequest) -> RunnerResponse {
+    pub fn handle_request(
+        &mut self,
+        request: RunnerRequest,
+        responder: &Responder,
+    ) -> RunnerResponse {
         if let RunnerAction::Create(create_action) = &request.action {
             if self.handlers.contains_key(&request.runner_name) {
                 return RunnerResponse::RunnerAlreadyExists(request.runner_name);
             }
 
-            let new_handler = match create_new(&create_action.runner_type) {
-                Some(val) => val,
-                None => {
-                    return RunnerResponse::RunnerTypeNotExisting(create_action.runner_type.clone())
-                }
+            let new_handler = match create_new(create_action) {
+                Ok(val) => val,
+                Err(response) => return response,
             };
 
             assert!(self
@@ -53,6 +42,12 @@ impl Router {
             None => return RunnerResponse::RunnerNotExisting(request.runner_name),
         };
 
-        handler.handle(request.action)
+        handler.handle(request.action, responder)
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -31,9 +31,9 @@ impl RunnerServer {
 
     pub fn serve(&mut self) {
         while let Ok(request) = self.channel.rx.recv() {
-            let response = self.router.handle_request(request);
+            let response = self.router.handle_request(request, &self.channel.tx);
 
-            if let Err(_) = self.channel.tx.send(response) {
+            if self.channel.tx.send(response).is_err() {
                 return;
             }
         }
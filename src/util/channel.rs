@@ -15,3 +15,12 @@ impl<ReqT, RespT> TwoWayChannel<ReqT, RespT> {
         )
     }
 }
+
+impl<ReqT, RespT> Clone for TwoWayChannel<ReqT, RespT> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            rx: self.rx.clone(),
+        }
+    }
+}
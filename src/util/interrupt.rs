@@ -0,0 +1,137 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+lazy_static! {
+    static ref GLOBAL: Interrupt = Interrupt::default();
+}
+
+/// The last signal `inst` received, which is forwarded to the running command and ends the
+/// execution once it is taken
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt {
+    signal: Arc<AtomicUsize>,
+    /// Whether a signal was received that was not cleared, even if it was taken already
+    received: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    /// The interrupt shared by the interpreter and the runners of `inst`
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Records `SIGINT` and `SIGTERM` instead of terminating right away, while another one received
+    /// afterwards terminates `inst` with `128` plus its number, e.g. if the cleanup after the first
+    /// one hangs
+    pub fn register(&self) -> io::Result<()> {
+        for signal in [SIGINT, SIGTERM] {
+            // The handlers are called in the order they were registered
+            signal_hook::flag::register_conditional_shutdown(
+                signal,
+                128 + signal,
+                self.received.clone(),
+            )?;
+            signal_hook::flag::register(signal, self.received.clone())?;
+            signal_hook::flag::register_usize(signal, self.signal.clone(), signal as usize)?;
+        }
+        Ok(())
+    }
+
+    pub fn trigger(&self, signal: i32) {
+        self.received.store(true, Ordering::SeqCst);
+        self.signal.store(signal as usize, Ordering::SeqCst);
+    }
+
+    /// The received signal, which stays pending until taken
+    pub fn pending(&self) -> Option<i32> {
+        match self.signal.load(Ordering::SeqCst) {
+            0 => None,
+            signal => Some(signal as i32),
+        }
+    }
+
+    pub fn take(&self) -> Option<i32> {
+        match self.signal.swap(0, Ordering::SeqCst) {
+            0 => None,
+            signal => Some(signal as i32),
+        }
+    }
+
+    /// Drops the pending signal if it is the given one, as if it was never received
+    pub fn clear(&self, signal: i32) {
+        if self
+            .signal
+            .compare_exchange(signal as usize, 0, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            self.received.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Sleeps for the duration, returning early once a signal is pending
+    pub fn sleep(&self, duration: Duration) {
+        let deadline = Instant::now().checked_add(duration);
+        while self.pending().is_none() {
+            let now = Instant::now();
+            let left = match deadline {
+                Some(deadline) if deadline > now => deadline - now,
+                Some(_) => return,
+                None => duration,
+            };
+            thread::sleep(left.min(POLL_INTERVAL));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ok_take() {
+        let interrupt = Interrupt::default();
+        assert_eq!(interrupt.pending(), None);
+
+        interrupt.trigger(SIGTERM);
+        assert_eq!(interrupt.pending(), Some(SIGTERM));
+        assert_eq!(interrupt.take(), Some(SIGTERM));
+        assert_eq!(interrupt.take(), None);
+    }
+
+    #[test]
+    fn ok_clear_only_given_signal() {
+        let interrupt = Interrupt::default();
+        interrupt.trigger(SIGTERM);
+        interrupt.clear(SIGINT);
+        assert_eq!(interrupt.pending(), Some(SIGTERM));
+        interrupt.clear(SIGTERM);
+        assert_eq!(interrupt.pending(), None);
+    }
+
+    #[test]
+    fn ok_sleep_ends_on_signal() {
+        let interrupt = Interrupt::default();
+        let trigger = interrupt.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            trigger.trigger(SIGINT);
+        });
+
+        let started = Instant::now();
+        interrupt.sleep(Duration::from_secs(10));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(interrupt.pending(), Some(SIGINT));
+    }
+}